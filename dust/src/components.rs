@@ -0,0 +1,1119 @@
+use crate::figure::{heat_color, format_tick, Figure, PlotArea, Scale, Trace, PALETTE};
+use crate::table::{Cell, CellEdit, Filter, Sort, TablePage};
+use crate::upload::{upload_chunk, Upload, CHUNK_SIZE};
+use leptos::*;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How long typing needs to pause before the generated `bind_<field>` of a text field sets it.
+pub const TEXT_INPUT_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(300);
+
+/// Wraps `f` so that it only runs once `delay` has passed without another call, with the value of
+/// the last call.
+pub fn debounced<T: 'static>(delay: std::time::Duration, f: impl Fn(T) + 'static) -> impl Fn(T) {
+    return debounced_with(f, move |callback| match set_timeout_with_handle(callback, delay) {
+        Ok(handle) => Some(move || handle.clear()),
+        Err(error) => {
+            logging::warn!("failed to set a timeout: {:?}", error);
+            None
+        }
+    });
+}
+
+// Debounces `f` with `schedule`, which runs a callback later and returns a function cancelling
+// it.
+fn debounced_with<T: 'static, C: FnOnce() + 'static>(
+    f: impl Fn(T) + 'static,
+    schedule: impl Fn(Box<dyn FnOnce()>) -> Option<C> + 'static,
+) -> impl Fn(T) {
+    let f = Rc::new(f);
+    let pending: Rc<std::cell::Cell<Option<C>>> = Rc::new(std::cell::Cell::new(None));
+    return move |value: T| {
+        if let Some(cancel) = pending.take() {
+            cancel();
+        }
+        let f = f.clone();
+        pending.set(schedule(Box::new(move || f(value))));
+    };
+}
+
+/// A field of a DustContext bound to a widget: its value, its setter, the error of its validation
+/// constraints and whether the context waits for the server. DustContexts create them with
+/// `field_<name>()`, e.g. `<Dropdown field=ctx.field_country() options=.../>`. Widgets can't take
+/// the `ctx.country` signal itself, since setting it needs to go through the context to run the
+/// callbacks depending on it.
+pub struct Field<T: 'static> {
+    pub value: Signal<T>,
+    pub error: Signal<Option<String>>,
+    pub loading: Signal<bool>,
+    set: Rc<dyn Fn(T)>,
+}
+
+impl<T: 'static> Field<T> {
+    pub fn new(value: Signal<T>, set: impl Fn(T) + 'static) -> Field<T> {
+        Field {
+            value,
+            error: Signal::derive(|| None),
+            loading: Signal::derive(|| false),
+            set: Rc::new(set),
+        }
+    }
+
+    pub fn with_error(mut self, error: Signal<Option<String>>) -> Self {
+        self.error = error;
+        return self;
+    }
+
+    pub fn with_loading(mut self, loading: Signal<bool>) -> Self {
+        self.loading = loading;
+        return self;
+    }
+
+    pub fn set(&self, value: T) {
+        (self.set)(value);
+    }
+}
+
+impl<T: 'static> Clone for Field<T> {
+    fn clone(&self) -> Self {
+        Field {
+            value: self.value,
+            error: self.error,
+            loading: self.loading,
+            set: self.set.clone(),
+        }
+    }
+}
+
+// Whether the widget is disabled: when asked to, or while loading if `disable_while_loading`.
+fn is_disabled<T>(
+    field: &Field<T>,
+    disabled: MaybeSignal<bool>,
+    disable_while_loading: bool,
+) -> Signal<bool> {
+    let loading = field.loading;
+    return Signal::derive(move || disabled.get() || (disable_while_loading && loading.get()));
+}
+
+fn widget_class(base: &'static str, class: Option<String>) -> String {
+    match class {
+        Some(class) => format!("{} {}", base, class),
+        None => base.to_string(),
+    }
+}
+
+// Radio groups need a name shared by their inputs, unique in the page.
+fn next_group_name() -> String {
+    static NEXT_GROUP: AtomicUsize = AtomicUsize::new(0);
+    return format!("dust-radio-{}", NEXT_GROUP.fetch_add(1, Ordering::Relaxed));
+}
+
+#[component]
+fn FieldError(error: Signal<Option<String>>) -> impl IntoView {
+    return move || error.get().map(|message| view! { <span class="dust-error">{message}</span> });
+}
+
+/// A `<select>` setting the field to the value of the selected option.
+#[component]
+pub fn Dropdown<T>(
+    field: Field<T>,
+    #[prop(into)] options: MaybeSignal<Vec<(T, String)>>,
+    #[prop(into, optional)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] disable_while_loading: bool,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView
+where
+    T: Clone + PartialEq + 'static,
+{
+    let is_disabled = is_disabled(&field, disabled, disable_while_loading);
+    let loading = field.loading;
+    let error = field.error;
+    let value = field.value;
+    let on_change = {
+        let options = options.clone();
+        move |event: ev::Event| {
+            let index = event_target_value(&event).parse::<usize>().ok();
+            let selected =
+                index.and_then(|index| options.with_untracked(|options| options.get(index).cloned()));
+            if let Some((selected, _)) = selected {
+                field.set(selected);
+            }
+        }
+    };
+    view! {
+        <select
+            class=widget_class("dust-dropdown", class)
+            class:dust-loading=loading
+            aria-busy=move || loading.get().to_string()
+            disabled=is_disabled
+            on:change=on_change
+        >
+            {move || {
+                options
+                    .get()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (option, label))| {
+                        view! {
+                            <option
+                                value=index.to_string()
+                                prop:selected=move || value.with(|value| *value == option)
+                            >
+                                {label}
+                            </option>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </select>
+        <FieldError error=error/>
+    }
+}
+
+/// An `<input type="range">` for numeric fields.
+#[component]
+pub fn Slider<T>(
+    field: Field<T>,
+    min: T,
+    max: T,
+    step: T,
+    #[prop(into, optional)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] disable_while_loading: bool,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView
+where
+    T: Clone + ToString + FromStr + 'static,
+{
+    let is_disabled = is_disabled(&field, disabled, disable_while_loading);
+    let loading = field.loading;
+    let error = field.error;
+    let value = field.value;
+    let on_input = move |event: ev::Event| {
+        if let Ok(value) = event_target_value(&event).parse::<T>() {
+            field.set(value);
+        }
+    };
+    view! {
+        <input
+            type="range"
+            class=widget_class("dust-slider", class)
+            class:dust-loading=loading
+            aria-busy=move || loading.get().to_string()
+            min=min.to_string()
+            max=max.to_string()
+            step=step.to_string()
+            disabled=is_disabled
+            value=move || value.with(|value| value.to_string())
+            prop:value=move || value.with(|value| value.to_string())
+            on:input=on_input
+        />
+        <FieldError error=error/>
+    }
+}
+
+/// A list of checkboxes, the field holds the values of the checked ones.
+#[component]
+pub fn Checklist<T>(
+    field: Field<Vec<T>>,
+    #[prop(into)] options: MaybeSignal<Vec<(T, String)>>,
+    #[prop(into, optional)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] disable_while_loading: bool,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView
+where
+    T: Clone + PartialEq + 'static,
+{
+    let is_disabled = is_disabled(&field, disabled, disable_while_loading);
+    let loading = field.loading;
+    let error = field.error;
+    let value = field.value;
+    view! {
+        <div
+            class=widget_class("dust-checklist", class)
+            class:dust-loading=loading
+            aria-busy=move || loading.get().to_string()
+        >
+            {move || {
+                options
+                    .get()
+                    .into_iter()
+                    .map(|(option, label)| {
+                        let field = field.clone();
+                        let checked_option = option.clone();
+                        let on_change = move |event: ev::Event| {
+                            let mut values = value.get_untracked();
+                            values.retain(|value| *value != option);
+                            if event_target_checked(&event) {
+                                values.push(option.clone());
+                            }
+                            field.set(values);
+                        };
+                        view! {
+                            <label>
+                                <input
+                                    type="checkbox"
+                                    disabled=is_disabled
+                                    prop:checked=move || value.with(|values| values.contains(&checked_option))
+                                    on:change=on_change
+                                />
+                                {label}
+                            </label>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </div>
+        <FieldError error=error/>
+    }
+}
+
+/// Radio buttons setting the field to the value of the checked one.
+#[component]
+pub fn RadioGroup<T>(
+    field: Field<T>,
+    #[prop(into)] options: MaybeSignal<Vec<(T, String)>>,
+    #[prop(into, optional)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] disable_while_loading: bool,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView
+where
+    T: Clone + PartialEq + 'static,
+{
+    let is_disabled = is_disabled(&field, disabled, disable_while_loading);
+    let loading = field.loading;
+    let error = field.error;
+    let value = field.value;
+    let name = next_group_name();
+    view! {
+        <div
+            class=widget_class("dust-radio-group", class)
+            class:dust-loading=loading
+            aria-busy=move || loading.get().to_string()
+        >
+            {move || {
+                options
+                    .get()
+                    .into_iter()
+                    .map(|(option, label)| {
+                        let field = field.clone();
+                        let checked_option = option.clone();
+                        view! {
+                            <label>
+                                <input
+                                    type="radio"
+                                    name=name.clone()
+                                    disabled=is_disabled
+                                    prop:checked=move || value.with(|value| *value == checked_option)
+                                    on:change=move |_| field.set(option.clone())
+                                />
+                                {label}
+                            </label>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </div>
+        <FieldError error=error/>
+    }
+}
+
+/// A text `<input>`, updating the field once typing pauses for `debounce` (`TEXT_INPUT_DEBOUNCE`
+/// by default), like the generated `bind_<field>`, or on change events (when the input loses
+/// focus) with `update_on_change`.
+#[component]
+pub fn TextInput(
+    field: Field<String>,
+    #[prop(into, optional)] placeholder: Option<String>,
+    #[prop(optional)] update_on_change: bool,
+    #[prop(optional)] debounce: Option<std::time::Duration>,
+    #[prop(into, optional)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] disable_while_loading: bool,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView {
+    let is_disabled = is_disabled(&field, disabled, disable_while_loading);
+    let loading = field.loading;
+    let error = field.error;
+    let value = field.value;
+    let on_input = {
+        let field = field.clone();
+        let set = debounced(debounce.unwrap_or(TEXT_INPUT_DEBOUNCE), move |value: String| {
+            field.set(value)
+        });
+        move |event: ev::Event| {
+            if !update_on_change {
+                set(event_target_value(&event));
+            }
+        }
+    };
+    let on_change = move |event: ev::Event| {
+        if update_on_change {
+            field.set(event_target_value(&event));
+        }
+    };
+    view! {
+        <input
+            type="text"
+            class=widget_class("dust-text-input", class)
+            class:dust-loading=loading
+            aria-busy=move || loading.get().to_string()
+            placeholder=placeholder
+            disabled=is_disabled
+            value=value
+            prop:value=value
+            on:input=on_input
+            on:change=on_change
+        />
+        <FieldError error=error/>
+    }
+}
+
+/// An `<input type="date">` for fields holding dates as `YYYY-MM-DD` strings.
+#[component]
+pub fn DatePicker(
+    field: Field<String>,
+    #[prop(into, optional)] min: Option<String>,
+    #[prop(into, optional)] max: Option<String>,
+    #[prop(into, optional)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] disable_while_loading: bool,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView {
+    let is_disabled = is_disabled(&field, disabled, disable_while_loading);
+    let loading = field.loading;
+    let error = field.error;
+    let value = field.value;
+    view! {
+        <input
+            type="date"
+            class=widget_class("dust-date-picker", class)
+            class:dust-loading=loading
+            aria-busy=move || loading.get().to_string()
+            min=min
+            max=max
+            disabled=is_disabled
+            value=value
+            prop:value=value
+            on:change=move |event| field.set(event_target_value(&event))
+        />
+        <FieldError error=error/>
+    }
+}
+
+/// Renders a Figure as SVG, e.g. `<Graph figure=ctx.chart/>` for a `chart: Figure` field set by a
+/// server callback.
+#[component]
+pub fn Graph(
+    #[prop(into)] figure: MaybeSignal<Figure>,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView {
+    view! {
+        <div class=widget_class("dust-graph", class)>
+            {move || figure.with(render_figure)}
+        </div>
+    }
+}
+
+fn render_figure(figure: &Figure) -> View {
+    let layout = &figure.layout;
+    let plot = PlotArea::new(figure);
+    let mut elements = render_axes(figure, &plot);
+
+    let bar_traces = figure.traces.iter().filter(|trace| matches!(trace, Trace::Bar(_))).count();
+    let mut bar_index = 0;
+    for (index, trace) in figure.traces.iter().enumerate() {
+        let color = trace.color().unwrap_or(PALETTE[index % PALETTE.len()]).to_string();
+        match trace {
+            Trace::Line(series) => {
+                // Non-finite values leave a gap in the line.
+                let mut path = String::new();
+                let mut pen_down = false;
+                for (x, y) in series.x.iter().zip(series.y.iter()) {
+                    if !x.is_finite() || !y.is_finite() {
+                        pen_down = false;
+                        continue;
+                    }
+                    let command = if pen_down { "L" } else { "M" };
+                    path.push_str(&format!(
+                        "{}{:.2} {:.2} ",
+                        command,
+                        plot.x.position(*x),
+                        plot.y.position(*y)
+                    ));
+                    pen_down = true;
+                }
+                elements.push(
+                    view! { <path d=path fill="none" stroke=color stroke-width="2"/> }.into_view(),
+                );
+            }
+            Trace::Scatter(series) => {
+                for (x, y) in series.x.iter().zip(series.y.iter()) {
+                    if !x.is_finite() || !y.is_finite() {
+                        continue;
+                    }
+                    elements.push(
+                        view! {
+                            <circle
+                                cx=plot.x.position(*x)
+                                cy=plot.y.position(*y)
+                                r="4"
+                                fill=color.clone()
+                                fill-opacity="0.8"
+                            />
+                        }
+                        .into_view(),
+                    );
+                }
+            }
+            Trace::Bar(bars) => {
+                // Bars of different traces are grouped side by side in the band of their category.
+                if let Scale::Bands(ref x) = plot.x {
+                    let inner = x.step() * 0.8;
+                    let width = inner / bar_traces as f64;
+                    for (category, value) in bars.categories.iter().zip(bars.values.iter()) {
+                        let Some(category_index) = x.index_of(category) else {
+                            continue;
+                        };
+                        let left = x.band_start(category_index as f64)
+                            + x.step() * 0.1
+                            + bar_index as f64 * width;
+                        let (y0, y1) = (plot.y.position(0.0), plot.y.position(*value));
+                        elements.push(
+                            view! {
+                                <rect
+                                    x=left
+                                    y=y0.min(y1)
+                                    width=width
+                                    height=(y1 - y0).abs()
+                                    fill=color.clone()
+                                />
+                            }
+                            .into_view(),
+                        );
+                    }
+                }
+                bar_index += 1;
+            }
+            Trace::Histogram(histogram) => {
+                for (start, end, count) in histogram.bin_counts() {
+                    let (x0, x1) = (plot.x.position(start), plot.x.position(end));
+                    let (y0, y1) = (plot.y.position(0.0), plot.y.position(count as f64));
+                    elements.push(
+                        view! {
+                            <rect
+                                x=x0 + 0.5
+                                y=y0.min(y1)
+                                width=(x1 - x0 - 1.0).max(0.0)
+                                height=(y1 - y0).abs()
+                                fill=color.clone()
+                            />
+                        }
+                        .into_view(),
+                    );
+                }
+            }
+            Trace::Heatmap(heatmap) => {
+                let (Scale::Bands(ref x), Scale::Bands(ref y)) = (&plot.x, &plot.y) else {
+                    continue;
+                };
+                let values = heatmap.z.iter().flatten().cloned().filter(|v| v.is_finite());
+                let min = values.clone().fold(f64::INFINITY, f64::min);
+                let max = values.fold(f64::NEG_INFINITY, f64::max);
+                for (row, row_values) in heatmap.z.iter().enumerate() {
+                    let Some(y_index) = heatmap.y.get(row).and_then(|c| y.index_of(c)) else {
+                        continue;
+                    };
+                    let (y0, y1) = (
+                        y.band_start(y_index as f64),
+                        y.band_start(y_index as f64 + 1.0),
+                    );
+                    for (column, value) in row_values.iter().enumerate() {
+                        let Some(x_index) = heatmap.x.get(column).and_then(|c| x.index_of(c))
+                        else {
+                            continue;
+                        };
+                        let t = if max > min { (value - min) / (max - min) } else { 0.5 };
+                        elements.push(
+                            view! {
+                                <rect
+                                    x=x.band_start(x_index as f64)
+                                    y=y0.min(y1)
+                                    width=x.step()
+                                    height=(y1 - y0).abs()
+                                    fill=heat_color(t)
+                                />
+                            }
+                            .into_view(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    if plot.show_legend {
+        let named_traces = figure
+            .traces
+            .iter()
+            .enumerate()
+            .filter(|(_, trace)| !trace.name().is_empty() && !matches!(trace, Trace::Heatmap(_)));
+        for (row, (index, trace)) in named_traces.enumerate() {
+            let color = trace.color().unwrap_or(PALETTE[index % PALETTE.len()]).to_string();
+            let y = plot.top + 10.0 + row as f64 * 20.0;
+            elements.push(
+                view! {
+                    <rect x=plot.right + 15.0 y=y - 6.0 width="12" height="12" fill=color/>
+                    <text x=plot.right + 32.0 y=y dominant-baseline="middle">
+                        {trace.name().to_string()}
+                    </text>
+                }
+                .into_view(),
+            );
+        }
+    }
+    if let Some(ref title) = layout.title {
+        elements.push(
+            view! {
+                <text x=layout.width as f64 / 2.0 y="24" text-anchor="middle" font-size="16">
+                    {title.clone()}
+                </text>
+            }
+            .into_view(),
+        );
+    }
+
+    return view! {
+        <svg
+            viewBox=format!("0 0 {} {}", layout.width, layout.height)
+            width="100%"
+            font-family="sans-serif"
+            font-size="12"
+        >
+            {elements}
+        </svg>
+    }
+    .into_view();
+}
+
+// Grid lines, ticks, tick labels and titles of both axes.
+fn render_axes(figure: &Figure, plot: &PlotArea) -> Vec<View> {
+    let layout = &figure.layout;
+    let mut elements = Vec::new();
+    match plot.y {
+        Scale::Linear(ref y) => {
+            for tick in y.ticks(5) {
+                let position = y.map(tick);
+                elements.push(
+                    view! {
+                        <line
+                            x1=plot.left
+                            x2=plot.right
+                            y1=position
+                            y2=position
+                            stroke="#e5e5e5"
+                        />
+                        <text
+                            x=plot.left - 8.0
+                            y=position
+                            text-anchor="end"
+                            dominant-baseline="middle"
+                        >
+                            {format_tick(tick)}
+                        </text>
+                    }
+                    .into_view(),
+                );
+            }
+        }
+        Scale::Bands(ref y) => {
+            for (index, category) in y.categories.iter().enumerate() {
+                elements.push(
+                    view! {
+                        <text
+                            x=plot.left - 8.0
+                            y=y.band_start(index as f64 + 0.5)
+                            text-anchor="end"
+                            dominant-baseline="middle"
+                        >
+                            {category.clone()}
+                        </text>
+                    }
+                    .into_view(),
+                );
+            }
+        }
+    }
+    let x_labels: Vec<(f64, String)> = match plot.x {
+        Scale::Linear(ref x) => {
+            x.ticks(6).into_iter().map(|tick| (x.map(tick), format_tick(tick))).collect()
+        }
+        Scale::Bands(ref x) => x
+            .categories
+            .iter()
+            .enumerate()
+            .map(|(index, category)| (x.band_start(index as f64 + 0.5), category.clone()))
+            .collect(),
+    };
+    for (position, label) in x_labels {
+        elements.push(
+            view! {
+                <line
+                    x1=position
+                    x2=position
+                    y1=plot.bottom
+                    y2=plot.bottom + 5.0
+                    stroke="#444"
+                />
+                <text x=position y=plot.bottom + 18.0 text-anchor="middle">
+                    {label}
+                </text>
+            }
+            .into_view(),
+        );
+    }
+    elements.push(
+        view! {
+            <line x1=plot.left x2=plot.right y1=plot.bottom y2=plot.bottom stroke="#444"/>
+            <line x1=plot.left x2=plot.left y1=plot.top y2=plot.bottom stroke="#444"/>
+        }
+        .into_view(),
+    );
+    if let Some(ref title) = layout.x_axis.title {
+        elements.push(
+            view! {
+                <text
+                    x=(plot.left + plot.right) / 2.0
+                    y=layout.height as f64 - 12.0
+                    text-anchor="middle"
+                >
+                    {title.clone()}
+                </text>
+            }
+            .into_view(),
+        );
+    }
+    if let Some(ref title) = layout.y_axis.title {
+        let center = (plot.top + plot.bottom) / 2.0;
+        elements.push(
+            view! {
+                <text
+                    x="16"
+                    y=center
+                    text-anchor="middle"
+                    transform=format!("rotate(-90 16 {})", center)
+                >
+                    {title.clone()}
+                </text>
+            }
+            .into_view(),
+        );
+    }
+    return elements;
+}
+
+/// A table of the rows of a `TablePage`, usually a field set by a server callback from the other
+/// fields bound here, so sorting, filtering and paging happen on the server:
+///
+/// `<DataTable data=ctx.orders sort=ctx.field_sort() filters=ctx.field_filters() page=ctx.field_page()/>`
+///
+/// Each feature is enabled by binding its field: clicking sortable headers cycles `sort` through
+/// ascending, descending and unsorted, `filters` adds an input under filterable headers, `page`
+/// adds pagination buttons, `selected` adds checkboxes holding the keys of the selected rows and
+/// `edit` makes editable cells inputs, each change setting it as an input update.
+#[component]
+pub fn DataTable(
+    #[prop(into)] data: MaybeSignal<TablePage>,
+    #[prop(optional)] sort: Option<Field<Option<Sort>>>,
+    #[prop(optional)] filters: Option<Field<Vec<Filter>>>,
+    #[prop(optional)] page: Option<Field<usize>>,
+    #[prop(optional)] selected: Option<Field<Vec<String>>>,
+    #[prop(optional)] edit: Option<Field<Option<CellEdit>>>,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView {
+    // All fields come from the same context, so any of them tells whether it's loading.
+    let loading = [
+        sort.as_ref().map(|field| field.loading),
+        filters.as_ref().map(|field| field.loading),
+        page.as_ref().map(|field| field.loading),
+        selected.as_ref().map(|field| field.loading),
+        edit.as_ref().map(|field| field.loading),
+    ]
+    .into_iter()
+    .flatten()
+    .next()
+    .unwrap_or_else(|| Signal::derive(|| false));
+    let header = {
+        let data = data.clone();
+        let selectable = selected.is_some();
+        move || {
+            data.with(|table| {
+                view! {
+                    <tr>
+                        {selectable.then(|| view! { <th></th> })}
+                        {table
+                            .columns
+                            .iter()
+                            .map(|column| table_header(&column.name, &column.title, column.sortable, &sort))
+                            .collect_view()}
+                    </tr>
+                    {filters.clone().map(|filters| {
+                        view! {
+                            <tr class="dust-table-filters">
+                                {selectable.then(|| view! { <th></th> })}
+                                {table
+                                    .columns
+                                    .iter()
+                                    .map(|column| {
+                                        column.filterable.then(|| filter_input(&column.name, &filters))
+                                    })
+                                    .map(|input| view! { <th>{input}</th> })
+                                    .collect_view()}
+                            </tr>
+                        }
+                    })}
+                }
+            })
+        }
+    };
+    let body = {
+        let data = data.clone();
+        move || {
+            data.with(|table| {
+                table
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let key = row.key.clone();
+                        let is_selected = selected.clone().map(|selected| {
+                            let key = key.clone();
+                            Signal::derive(move || selected.value.with(|keys| keys.contains(&key)))
+                        });
+                        view! {
+                            <tr class:dust-selected=move || is_selected.is_some_and(|s| s.get())>
+                                {selected.clone().map(|selected| row_checkbox(key.clone(), selected))}
+                                {table
+                                    .columns
+                                    .iter()
+                                    .zip(row.cells.iter().chain(std::iter::repeat(&Cell::Empty)))
+                                    .map(|(column, cell)| match edit {
+                                        Some(ref edit) if column.editable => {
+                                            let edit = edit.clone();
+                                            let (row, column) = (key.clone(), column.name.clone());
+                                            let previous = cell.clone();
+                                            let on_change = move |event: ev::Event| {
+                                                edit.set(Some(CellEdit {
+                                                    row: row.clone(),
+                                                    column: column.clone(),
+                                                    value: previous.parse_like(&event_target_value(&event)),
+                                                }));
+                                            };
+                                            let text = cell.to_string();
+                                            view! {
+                                                <td>
+                                                    <input
+                                                        type="text"
+                                                        value=text.clone()
+                                                        prop:value=text
+                                                        on:change=on_change
+                                                    />
+                                                </td>
+                                            }
+                                        }
+                                        _ => view! { <td>{cell.to_string()}</td> },
+                                    })
+                                    .collect_view()}
+                            </tr>
+                        }
+                    })
+                    .collect_view()
+            })
+        }
+    };
+    let pagination = page.map(|page| {
+        let counts =
+            Signal::derive(move || data.with(|table| (table.page, table.page_count(), table.total_rows)));
+        let current = Signal::derive(move || counts.get().0);
+        let is_last = Signal::derive(move || counts.with(|(page, page_count, _)| page + 1 >= *page_count));
+        let previous = page.clone();
+        view! {
+            <div class="dust-pagination">
+                <button
+                    disabled=move || current.get() == 0
+                    on:click=move |_| previous.set(current.get_untracked().saturating_sub(1))
+                >
+                    "Previous"
+                </button>
+                <span>
+                    {move || {
+                        let (current, page_count, total_rows) = counts.get();
+                        format!("Page {} of {} ({} rows)", current + 1, page_count, total_rows)
+                    }}
+                </span>
+                <button
+                    disabled=is_last
+                    on:click=move |_| page.set(current.get_untracked() + 1)
+                >
+                    "Next"
+                </button>
+            </div>
+        }
+    });
+    view! {
+        <div
+            class=widget_class("dust-data-table", class)
+            class:dust-loading=loading
+            aria-busy=move || loading.get().to_string()
+        >
+            <table>
+                <thead>{header}</thead>
+                <tbody>{body}</tbody>
+            </table>
+            {pagination}
+        </div>
+    }
+}
+
+fn table_header(
+    name: &str,
+    title: &str,
+    sortable: bool,
+    sort: &Option<Field<Option<Sort>>>,
+) -> View {
+    let title = title.to_string();
+    let Some(sort) = sort.clone().filter(|_| sortable) else {
+        return view! { <th>{title}</th> }.into_view();
+    };
+    let name = name.to_string();
+    let direction = {
+        let name = name.clone();
+        let value = sort.value;
+        Signal::derive(move || {
+            value.with(|sort| match sort {
+                Some(sort) if sort.column == name => Some(sort.descending),
+                _ => None,
+            })
+        })
+    };
+    let on_click = move |_| {
+        let next = match direction.get_untracked() {
+            None => Some(Sort { column: name.clone(), descending: false }),
+            Some(false) => Some(Sort { column: name.clone(), descending: true }),
+            Some(true) => None,
+        };
+        sort.set(next);
+    };
+    return view! {
+        <th
+            class="dust-sortable"
+            aria-sort=move || match direction.get() {
+                None => "none",
+                Some(false) => "ascending",
+                Some(true) => "descending",
+            }
+            on:click=on_click
+        >
+            {title}
+            {move || match direction.get() {
+                None => "",
+                Some(false) => " ▲",
+                Some(true) => " ▼",
+            }}
+        </th>
+    }
+    .into_view();
+}
+
+fn filter_input(name: &str, filters: &Field<Vec<Filter>>) -> View {
+    let name = name.to_string();
+    let value = filters.value;
+    let expression = {
+        let name = name.clone();
+        move || {
+            value.with(|filters| {
+                filters
+                    .iter()
+                    .find(|filter| filter.column == name)
+                    .map(|filter| filter.expression.clone())
+                    .unwrap_or_default()
+            })
+        }
+    };
+    let filters = filters.clone();
+    let on_change = move |event: ev::Event| {
+        let expression = event_target_value(&event);
+        let mut values = value.get_untracked();
+        values.retain(|filter| filter.column != name);
+        if !expression.trim().is_empty() {
+            values.push(Filter { column: name.clone(), expression });
+        }
+        filters.set(values);
+    };
+    return view! {
+        <input
+            type="text"
+            placeholder="Filter"
+            value=expression.clone()
+            prop:value=expression
+            on:change=on_change
+        />
+    }
+    .into_view();
+}
+
+fn row_checkbox(key: String, selected: Field<Vec<String>>) -> View {
+    let value = selected.value;
+    let checked = {
+        let key = key.clone();
+        move || value.with(|keys| keys.contains(&key))
+    };
+    let on_change = move |event: ev::Event| {
+        let mut keys = value.get_untracked();
+        keys.retain(|selected| *selected != key);
+        if event_target_checked(&event) {
+            keys.push(key.clone());
+        }
+        selected.set(keys);
+    };
+    return view! {
+        <td>
+            <input type="checkbox" checked=checked.clone() prop:checked=checked on:change=on_change/>
+        </td>
+    }
+    .into_view();
+}
+
+/// An `<input type="file">` uploading the chosen file in chunks, then setting the field to it.
+#[component]
+pub fn FileUpload(
+    field: Field<Upload>,
+    // File types the browser offers to pick, e.g. `".csv,text/csv"`.
+    #[prop(into, optional)] accept: Option<String>,
+    #[prop(into, optional)] disabled: MaybeSignal<bool>,
+    #[prop(optional)] disable_while_loading: bool,
+    #[prop(into, optional)] class: Option<String>,
+) -> impl IntoView {
+    let is_disabled = is_disabled(&field, disabled, disable_while_loading);
+    let loading = field.loading;
+    let error = field.error;
+    let value = field.value;
+    // Fraction of the file sent while uploading, and the error of the last upload.
+    let progress = create_rw_signal(None::<f64>);
+    let upload_error = create_rw_signal(None::<String>);
+    let on_change = move |event: ev::Event| {
+        let input: web_sys::HtmlInputElement = event_target(&event);
+        let Some(file) = input.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        let field = field.clone();
+        spawn_local(async move {
+            upload_error.set(None);
+            progress.set(Some(0.0));
+            match upload_file(&file, progress).await {
+                Ok(upload) => field.set(upload),
+                Err(message) => upload_error.set(Some(message)),
+            }
+            progress.set(None);
+        });
+    };
+    view! {
+        <div
+            class=widget_class("dust-file-upload", class)
+            class:dust-loading=move || loading.get() || progress.with(Option::is_some)
+            aria-busy=move || (loading.get() || progress.with(Option::is_some)).to_string()
+        >
+            <input
+                type="file"
+                accept=accept
+                disabled=move || is_disabled.get() || progress.with(Option::is_some)
+                on:change=on_change
+            />
+            {move || {
+                progress
+                    .get()
+                    .map(|progress| view! { <progress max="1" value=progress></progress> })
+            }}
+            {move || {
+                value.with(|upload| {
+                    (!upload.is_empty())
+                        .then(|| view! { <span class="dust-file-name">{upload.name.clone()}</span> })
+                })
+            }}
+            {move || {
+                upload_error.get().map(|message| view! { <span class="dust-error">{message}</span> })
+            }}
+        </div>
+        <FieldError error=error/>
+    }
+}
+
+// Sends the file chunk by chunk, reading each one only when it's sent.
+async fn upload_file(file: &web_sys::File, progress: RwSignal<Option<f64>>) -> Result<Upload, String> {
+    let size = file.size() as usize;
+    let mut id = String::new();
+    let mut offset = 0;
+    // Empty files are still uploaded, with a single empty chunk.
+    while offset < size || id.is_empty() {
+        let end = (offset + CHUNK_SIZE).min(size);
+        let data = read_chunk(file, offset, end).await?;
+        id = upload_chunk(id, file.name(), file.type_(), size, offset, data)
+            .await
+            .map_err(|error| format!("failed to upload {}: {}", file.name(), error))?;
+        offset = end;
+        progress.set(Some(offset as f64 / size.max(1) as f64));
+    }
+    return Ok(Upload {
+        id,
+        name: file.name(),
+        mime_type: file.type_(),
+        size,
+    });
+}
+
+async fn read_chunk(file: &web_sys::File, start: usize, end: usize) -> Result<Vec<u8>, String> {
+    let read_error = |_| format!("failed to read {}", file.name());
+    let chunk = file
+        .slice_with_f64_and_f64(start as f64, end as f64)
+        .map_err(read_error)?;
+    let buffer = wasm_bindgen_futures::JsFuture::from(chunk.array_buffer())
+        .await
+        .map_err(read_error)?;
+    return Ok(js_sys::Uint8Array::new(&buffer).to_vec());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    // Callbacks scheduled by a debounced function, None once cancelled.
+    type Scheduled = Rc<RefCell<Vec<Option<Box<dyn FnOnce()>>>>>;
+
+    #[test]
+    fn debounced_runs_the_last_call_only() {
+        let scheduled: Scheduled = Rc::new(RefCell::new(Vec::new()));
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        let debounced = {
+            let scheduled = scheduled.clone();
+            let calls = calls.clone();
+            debounced_with(
+                move |value: &str| calls.borrow_mut().push(value.to_string()),
+                move |callback| {
+                    let mut callbacks = scheduled.borrow_mut();
+                    callbacks.push(Some(callback));
+                    let index = callbacks.len() - 1;
+                    let scheduled = scheduled.clone();
+                    Some(move || scheduled.borrow_mut()[index] = None)
+                },
+            )
+        };
+
+        debounced("a");
+        debounced("ab");
+        debounced("abc");
+        let pending: Vec<Box<dyn FnOnce()>> = scheduled.borrow_mut().drain(..).flatten().collect();
+        assert_eq!(pending.len(), 1);
+        for callback in pending {
+            callback();
+        }
+        assert_eq!(*calls.borrow(), vec!["abc"]);
+    }
+}
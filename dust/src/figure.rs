@@ -0,0 +1,546 @@
+use serde::{Deserialize, Serialize};
+
+/// A chart made of traces drawn on shared axes, rendered as SVG by `dust::components::Graph`.
+/// It's serializable, so it can be a state field set by server callbacks:
+///
+/// `Figure::new().with_title("Sales").with_trace(Trace::line("2024", months, totals))`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Figure {
+    pub traces: Vec<Trace>,
+    pub layout: Layout,
+}
+
+impl Figure {
+    pub fn new() -> Figure {
+        return Figure::default();
+    }
+
+    pub fn with_trace(mut self, trace: Trace) -> Self {
+        self.traces.push(trace);
+        return self;
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.layout.title = Some(title.into());
+        return self;
+    }
+
+    pub fn with_x_axis(mut self, axis: Axis) -> Self {
+        self.layout.x_axis = axis;
+        return self;
+    }
+
+    pub fn with_y_axis(mut self, axis: Axis) -> Self {
+        self.layout.y_axis = axis;
+        return self;
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.layout.width = width;
+        self.layout.height = height;
+        return self;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub title: Option<String>,
+    // Size of the SVG viewBox, the chart scales with the width of its container.
+    pub width: u32,
+    pub height: u32,
+    pub x_axis: Axis,
+    pub y_axis: Axis,
+    pub show_legend: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            title: None,
+            width: 640,
+            height: 400,
+            x_axis: Axis::default(),
+            y_axis: Axis::default(),
+            show_legend: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Axis {
+    pub title: Option<String>,
+    // Fixed (min, max) of a numeric axis, computed from the traces otherwise.
+    pub range: Option<(f64, f64)>,
+}
+
+impl Axis {
+    pub fn new() -> Axis {
+        return Axis::default();
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        return self;
+    }
+
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        return self;
+    }
+}
+
+/// Bar and heatmap traces use categorical axes. Line and scatter traces drawn along them take the
+/// index of the category as x.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Trace {
+    Line(Series),
+    Scatter(Series),
+    Bar(Bars),
+    Histogram(Histogram),
+    Heatmap(Heatmap),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Series {
+    pub name: String,
+    pub x: Vec<f64>,
+    pub y: Vec<f64>,
+    pub color: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bars {
+    pub name: String,
+    pub categories: Vec<String>,
+    pub values: Vec<f64>,
+    pub color: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    pub name: String,
+    pub values: Vec<f64>,
+    // Number of equal width bins between the smallest and largest value.
+    pub bins: usize,
+    pub color: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Heatmap {
+    pub name: String,
+    pub x: Vec<String>,
+    pub y: Vec<String>,
+    // One row per entry of `y`, with one value per entry of `x`.
+    pub z: Vec<Vec<f64>>,
+}
+
+impl Trace {
+    pub fn line(name: impl Into<String>, x: Vec<f64>, y: Vec<f64>) -> Trace {
+        return Trace::Line(Series {
+            name: name.into(),
+            x,
+            y,
+            color: None,
+        });
+    }
+
+    pub fn scatter(name: impl Into<String>, x: Vec<f64>, y: Vec<f64>) -> Trace {
+        return Trace::Scatter(Series {
+            name: name.into(),
+            x,
+            y,
+            color: None,
+        });
+    }
+
+    pub fn bar(name: impl Into<String>, categories: Vec<String>, values: Vec<f64>) -> Trace {
+        return Trace::Bar(Bars {
+            name: name.into(),
+            categories,
+            values,
+            color: None,
+        });
+    }
+
+    pub fn histogram(name: impl Into<String>, values: Vec<f64>, bins: usize) -> Trace {
+        return Trace::Histogram(Histogram {
+            name: name.into(),
+            values,
+            bins,
+            color: None,
+        });
+    }
+
+    pub fn heatmap(
+        name: impl Into<String>,
+        x: Vec<String>,
+        y: Vec<String>,
+        z: Vec<Vec<f64>>,
+    ) -> Trace {
+        return Trace::Heatmap(Heatmap {
+            name: name.into(),
+            x,
+            y,
+            z,
+        });
+    }
+
+    // Heatmaps are colored by their values and ignore it.
+    pub fn with_color(mut self, color: impl Into<String>) -> Self {
+        match self {
+            Trace::Line(ref mut series) | Trace::Scatter(ref mut series) => {
+                series.color = Some(color.into())
+            }
+            Trace::Bar(ref mut bars) => bars.color = Some(color.into()),
+            Trace::Histogram(ref mut histogram) => histogram.color = Some(color.into()),
+            Trace::Heatmap(_) => {}
+        }
+        return self;
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Trace::Line(series) | Trace::Scatter(series) => &series.name,
+            Trace::Bar(bars) => &bars.name,
+            Trace::Histogram(histogram) => &histogram.name,
+            Trace::Heatmap(heatmap) => &heatmap.name,
+        }
+    }
+
+    pub fn color(&self) -> Option<&str> {
+        match self {
+            Trace::Line(series) | Trace::Scatter(series) => series.color.as_deref(),
+            Trace::Bar(bars) => bars.color.as_deref(),
+            Trace::Histogram(histogram) => histogram.color.as_deref(),
+            Trace::Heatmap(_) => None,
+        }
+    }
+}
+
+// Limits of what a figure renders, whatever its data asks for.
+const MAX_BINS: usize = 1000;
+const MAX_TICKS: usize = 1000;
+
+impl Histogram {
+    // Returns the (start, end, count) of each bin, at most MAX_BINS of them.
+    pub fn bin_counts(&self) -> Vec<(f64, f64, usize)> {
+        let values: Vec<f64> = self.values.iter().cloned().filter(|v| v.is_finite()).collect();
+        if values.is_empty() {
+            return Vec::new();
+        }
+        let bins = if self.bins == 0 { 10 } else { self.bins.min(MAX_BINS) };
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let width = if max > min { (max - min) / bins as f64 } else { 1.0 };
+        let mut counts = vec![0; bins];
+        for value in values {
+            let bin = (((value - min) / width) as usize).min(bins - 1);
+            counts[bin] += 1;
+        }
+        return counts
+            .into_iter()
+            .enumerate()
+            .map(|(bin, count)| {
+                let start = min + bin as f64 * width;
+                (start, start + width, count)
+            })
+            .collect();
+    }
+}
+
+// Plotly's default colors, used by traces without a color.
+pub(crate) const PALETTE: [&str; 10] = [
+    "#636efa", "#ef553b", "#00cc96", "#ab63fa", "#ffa15a", "#19d3f3", "#ff6692", "#b6e880",
+    "#ff97ff", "#fecb52",
+];
+
+// Color of a heatmap cell, `t` going from 0 (lowest value) to 1 (highest value).
+pub(crate) fn heat_color(t: f64) -> String {
+    let t = if t.is_finite() { t.clamp(0.0, 1.0) } else { 0.0 };
+    let (from, to) = ((247.0, 251.0, 255.0), (8.0, 48.0, 107.0));
+    let mix = |from: f64, to: f64| (from + (to - from) * t).round() as u8;
+    return format!("rgb({},{},{})", mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2));
+}
+
+// Maps values of a numeric axis to SVG coordinates.
+#[derive(Clone, Debug)]
+pub(crate) struct LinearScale {
+    pub min: f64,
+    pub max: f64,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl LinearScale {
+    pub fn map(&self, value: f64) -> f64 {
+        return self.start + (value - self.min) / (self.max - self.min) * (self.end - self.start);
+    }
+
+    // Round values between min and max, about `count` of them.
+    pub fn ticks(&self, count: usize) -> Vec<f64> {
+        let raw_step = (self.max - self.min) / count.max(1) as f64;
+        let magnitude = 10f64.powf(raw_step.log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| *step >= raw_step)
+            .unwrap_or(10.0 * magnitude);
+        if !step.is_finite() || step <= 0.0 {
+            // min == max, or bounds that aren't finite.
+            return if self.min.is_finite() { vec![self.min] } else { Vec::new() };
+        }
+        let first = (self.min / step).ceil() * step;
+        let mut ticks = Vec::new();
+        let mut previous = f64::NEG_INFINITY;
+        for index in 0..MAX_TICKS {
+            let tick = first + index as f64 * step;
+            // Ticks stop advancing when the step is below the float spacing of large values, e.g.
+            // an axis of nanosecond timestamps.
+            if tick > self.max + step * 1e-9 || tick <= previous {
+                break;
+            }
+            previous = tick;
+            // Avoids printing -0 and float noise like 0.30000000000000004.
+            ticks.push(if tick.abs() < step * 1e-9 { 0.0 } else { tick });
+        }
+        return ticks;
+    }
+}
+
+// Maps the categories of a categorical axis to equal width bands.
+#[derive(Clone, Debug)]
+pub(crate) struct BandScale {
+    pub categories: Vec<String>,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl BandScale {
+    pub fn step(&self) -> f64 {
+        return (self.end - self.start) / self.categories.len().max(1) as f64;
+    }
+
+    pub fn index_of(&self, category: &str) -> Option<usize> {
+        return self.categories.iter().position(|c| c == category);
+    }
+
+    // Start of the band of the category at `index`.
+    pub fn band_start(&self, index: f64) -> f64 {
+        return self.start + index * self.step();
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Scale {
+    Linear(LinearScale),
+    Bands(BandScale),
+}
+
+impl Scale {
+    // Position of a numeric value, the index of the category on categorical axes.
+    pub fn position(&self, value: f64) -> f64 {
+        match self {
+            Scale::Linear(scale) => scale.map(value),
+            Scale::Bands(scale) => scale.band_start(value + 0.5),
+        }
+    }
+}
+
+// Range of the values, padded so that points don't sit on the plot borders, and including zero
+// when bars start from it.
+fn numeric_domain(values: &[f64], include_zero: bool, fixed: Option<(f64, f64)>) -> (f64, f64) {
+    if let Some((min, max)) = fixed {
+        if max > min {
+            return (min, max);
+        }
+    }
+    let mut min = values.iter().cloned().filter(|v| v.is_finite()).fold(f64::INFINITY, f64::min);
+    let mut max = values.iter().cloned().filter(|v| v.is_finite()).fold(f64::NEG_INFINITY, f64::max);
+    if include_zero {
+        min = min.min(0.0);
+        max = max.max(0.0);
+    }
+    if min > max {
+        return (0.0, 1.0);
+    }
+    if min == max {
+        return (min - 1.0, max + 1.0);
+    }
+    let padding = (max - min) * 0.05;
+    return (
+        if include_zero && min == 0.0 { min } else { min - padding },
+        if include_zero && max == 0.0 { max } else { max + padding },
+    );
+}
+
+fn push_categories(categories: &mut Vec<String>, new_categories: &[String]) {
+    for category in new_categories {
+        if !categories.contains(category) {
+            categories.push(category.clone());
+        }
+    }
+}
+
+// Position of the plot inside the SVG and the scales of its axes.
+pub(crate) struct PlotArea {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub x: Scale,
+    pub y: Scale,
+    pub show_legend: bool,
+}
+
+impl PlotArea {
+    pub fn new(figure: &Figure) -> PlotArea {
+        let layout = &figure.layout;
+        let show_legend = layout.show_legend
+            && figure.traces.iter().filter(|trace| !trace.name().is_empty()).count() > 1;
+        let left = if layout.y_axis.title.is_some() { 70.0 } else { 50.0 };
+        let top = if layout.title.is_some() { 40.0 } else { 15.0 };
+        let right = layout.width as f64 - if show_legend { 130.0 } else { 20.0 };
+        let bottom = layout.height as f64 - if layout.x_axis.title.is_some() { 55.0 } else { 35.0 };
+
+        let mut x_categories = Vec::new();
+        let mut y_categories = Vec::new();
+        let mut x_values = Vec::new();
+        let mut y_values = Vec::new();
+        let mut has_bars = false;
+        for trace in figure.traces.iter() {
+            match trace {
+                Trace::Line(series) | Trace::Scatter(series) => {
+                    x_values.extend(series.x.iter().cloned());
+                    y_values.extend(series.y.iter().cloned());
+                }
+                Trace::Bar(bars) => {
+                    has_bars = true;
+                    push_categories(&mut x_categories, &bars.categories);
+                    y_values.extend(bars.values.iter().cloned());
+                }
+                Trace::Histogram(histogram) => {
+                    has_bars = true;
+                    for (start, end, count) in histogram.bin_counts() {
+                        x_values.push(start);
+                        x_values.push(end);
+                        y_values.push(count as f64);
+                    }
+                }
+                Trace::Heatmap(heatmap) => {
+                    push_categories(&mut x_categories, &heatmap.x);
+                    push_categories(&mut y_categories, &heatmap.y);
+                }
+            }
+        }
+
+        let x = if x_categories.is_empty() {
+            let (min, max) = numeric_domain(&x_values, false, layout.x_axis.range);
+            Scale::Linear(LinearScale { min, max, start: left, end: right })
+        } else {
+            Scale::Bands(BandScale { categories: x_categories, start: left, end: right })
+        };
+        // Categorical y axes go upwards, like numeric ones.
+        let y = if y_categories.is_empty() {
+            let (min, max) = numeric_domain(&y_values, has_bars, layout.y_axis.range);
+            Scale::Linear(LinearScale { min, max, start: bottom, end: top })
+        } else {
+            Scale::Bands(BandScale { categories: y_categories, start: bottom, end: top })
+        };
+        return PlotArea {
+            left,
+            top,
+            right,
+            bottom,
+            x,
+            y,
+            show_legend,
+        };
+    }
+}
+
+pub(crate) fn format_tick(value: f64) -> String {
+    let rounded = (value * 1e6).round() / 1e6;
+    if rounded.fract() == 0.0 && rounded.abs() < 1e15 {
+        return format!("{}", rounded as i64);
+    }
+    return format!("{}", rounded);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scale(min: f64, max: f64) -> LinearScale {
+        LinearScale {
+            min,
+            max,
+            start: 0.0,
+            end: 100.0,
+        }
+    }
+
+    fn histogram(values: Vec<f64>, bins: usize) -> Histogram {
+        Histogram {
+            values,
+            bins,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ticks_are_round_values_within_the_range() {
+        assert_eq!(scale(0.0, 1.0).ticks(5), vec![0.0, 0.2, 0.4, 0.6000000000000001, 0.8, 1.0]);
+        assert_eq!(scale(-3.0, 7.0).ticks(5), vec![-2.0, 0.0, 2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn ticks_of_a_single_value() {
+        assert_eq!(scale(4.0, 4.0).ticks(5), vec![4.0]);
+        assert!(scale(f64::NAN, f64::NAN).ticks(5).is_empty());
+    }
+
+    #[test]
+    fn ticks_stop_when_the_step_is_below_the_float_spacing() {
+        let ticks = scale(1e17, 1e17 + 16.0).ticks(5);
+        assert!(!ticks.is_empty() && ticks.len() <= 16);
+        assert!(ticks.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn ticks_are_bounded() {
+        assert!(scale(0.0, 1.0).ticks(usize::MAX).len() <= MAX_TICKS);
+    }
+
+    #[test]
+    fn bin_counts_put_the_largest_value_in_the_last_bin() {
+        let counts = histogram(vec![0.0, 2.0, 5.0, 10.0], 2).bin_counts();
+        assert_eq!(counts, vec![(0.0, 5.0, 2), (5.0, 10.0, 2)]);
+    }
+
+    #[test]
+    fn bin_counts_skip_values_that_arent_finite() {
+        let counts = histogram(vec![f64::NAN, 1.0, f64::INFINITY], 1).bin_counts();
+        assert_eq!(counts, vec![(1.0, 2.0, 1)]);
+        assert!(histogram(vec![f64::NAN], 1).bin_counts().is_empty());
+    }
+
+    #[test]
+    fn bin_counts_default_and_limit_the_number_of_bins() {
+        assert_eq!(histogram(vec![0.0, 1.0], 0).bin_counts().len(), 10);
+        assert_eq!(histogram(vec![0.0, 1.0], usize::MAX).bin_counts().len(), MAX_BINS);
+    }
+
+    #[test]
+    fn numeric_domain_is_padded() {
+        assert_eq!(numeric_domain(&[0.0, 10.0], false, None), (-0.5, 10.5));
+        assert_eq!(numeric_domain(&[2.0, 10.0, f64::NAN], true, None), (0.0, 10.5));
+        assert_eq!(numeric_domain(&[3.0], false, None), (2.0, 4.0));
+        assert_eq!(numeric_domain(&[], false, None), (0.0, 1.0));
+    }
+
+    #[test]
+    fn numeric_domain_keeps_a_valid_fixed_range() {
+        assert_eq!(numeric_domain(&[0.0, 10.0], false, Some((1.0, 2.0))), (1.0, 2.0));
+        assert_eq!(numeric_domain(&[0.0, 10.0], false, Some((2.0, 2.0))), (-0.5, 10.5));
+    }
+}
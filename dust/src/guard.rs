@@ -0,0 +1,55 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Authorization check attached to a callback through
+/// `#[dust_define_callback(State, guard = ...)]`. It's evaluated against the incoming request on
+/// the server when the callback is reached in the execution plan. A rejected callback is skipped,
+/// along with the callbacks depending on its outputs, and the error is sent to the client.
+type Check = dyn Fn(&http::request::Parts) -> Result<(), String> + Send + Sync;
+
+#[derive(Clone)]
+pub struct Guard {
+    check: Arc<Check>,
+}
+
+impl Guard {
+    pub fn new(
+        check: impl Fn(&http::request::Parts) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Guard {
+        Guard {
+            check: Arc::new(check),
+        }
+    }
+
+    pub fn check(&self, request: &http::request::Parts) -> Result<(), String> {
+        (self.check)(request)
+    }
+}
+
+/// Roles of the user making a request. Dust doesn't authenticate users: the application's
+/// authentication layer (e.g. an axum middleware) inserts them into the request extensions.
+#[derive(Clone, Debug, Default)]
+pub struct Roles(pub HashSet<String>);
+
+impl Roles {
+    pub fn contains(&self, role: &str) -> bool {
+        return self.0.contains(role);
+    }
+}
+
+/// Guard accepting requests whose `Roles` extension contains `role`:
+///
+/// ```ignore
+/// #[dust_define_callback(Admin, guard = require_role("admin"))]
+/// fn refund(order: Input<u64>, refunded: &mut Output<bool>) { ... }
+/// ```
+pub fn require_role(role: impl Into<String>) -> Guard {
+    let role = role.into();
+    return Guard::new(move |request| {
+        match request.extensions.get::<Roles>() {
+            Some(roles) if roles.contains(&role) => Ok(()),
+            Some(_) => Err(format!("the {} role is required", role)),
+            None => Err("the request is not authenticated".to_string()),
+        }
+    });
+}
@@ -1,15 +1,34 @@
+// Functions end with an explicit `return`.
+#![allow(clippy::needless_return)]
+
 use leptos::logging::log;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::hash::Hash;
 
+pub mod components;
+pub mod figure;
 pub mod file_handler;
+pub mod guard;
+pub mod resource;
 pub mod serve;
+pub mod signing;
+pub mod table;
+pub mod upload;
 
-pub use dust_macro::{DustState, dust_define_callback, dust_lib, dust_main};
+pub use dust_macro::{
+    DustState, dust_computed, dust_define_callback, dust_define_callbacks, dust_lib, dust_main,
+};
+pub use figure::{Axis, Figure, Trace};
+pub use guard::{Guard, Roles, require_role};
+pub use resource::{Resource, Resources};
+pub use signing::SignedValue;
+pub use upload::Upload;
 
 // Re-exports
 pub use console_error_panic_hook;
+pub use http;
+pub use inventory;
 pub use leptos;
 pub use leptos_meta;
 pub use leptos_router;
@@ -24,6 +43,7 @@ pub use wasm_bindgen;
 
 pub use web_sys;
 
+// Callbacks can take `Input<&T>` to borrow the field instead of getting a copy of it.
 pub struct Input<T> {
     pub value: T,
 }
@@ -34,15 +54,95 @@ pub enum OutputState {
     Updated,
 }
 
-pub struct Output<T> {
-    pub value: T,
+/// Field computed by a callback. Read it with `get()` or `*output` and update it with `set`,
+/// `update` or `*output = value`, which mark it as updated.
+pub struct Output<'a, T> {
+    // Value of the field before the callback ran, only copied if the callback needs it.
+    current: Option<&'a T>,
+    value: Option<T>,
     pub state: OutputState,
 }
 
-impl<T: Clone> Output<T> {
-    pub fn new(value: T) -> Output<T> {
+impl<'a, T: Clone> Output<'a, T> {
+    pub fn new(value: T) -> Output<'a, T> {
         Output {
-            value: value,
+            current: None,
+            value: Some(value),
+            state: OutputState::NoChange,
+        }
+    }
+
+    // Output borrowing the current value of the field.
+    pub fn lazy(current: &'a T) -> Output<'a, T> {
+        Output {
+            current: Some(current),
+            value: None,
+            state: OutputState::NoChange,
+        }
+    }
+
+    pub fn get(&self) -> &T {
+        match self.value {
+            Some(ref value) => value,
+            None => self.current.expect("Output has either a value or a current value"),
+        }
+    }
+
+    pub fn set(&mut self, value: T) {
+        self.value = Some(value);
+        self.state = OutputState::Updated;
+    }
+
+    // Mutable access to the value, copying the current one first if needed. Marks the output as
+    // updated.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.state = OutputState::Updated;
+        let current = self.current;
+        return self.value.get_or_insert_with(|| {
+            current
+                .expect("Output has either a value or a current value")
+                .clone()
+        });
+    }
+
+    pub fn update(&mut self, f: impl FnOnce(&mut T)) {
+        f(self.get_mut());
+    }
+
+    // Returns the new value if the callback updated the output.
+    pub fn into_update(self) -> Option<T> {
+        match self.state {
+            OutputState::NoChange => None,
+            OutputState::Updated => self.value,
+        }
+    }
+}
+
+impl<'a, T: Clone> std::ops::Deref for Output<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.get()
+    }
+}
+
+impl<'a, T: Clone> std::ops::DerefMut for Output<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.get_mut()
+    }
+}
+
+// Field that a callback reads and updates in place, like a running total or an appended log.
+// Updating it doesn't trigger the callback again, so it doesn't count as a cycle.
+pub struct InOut<T> {
+    pub value: T,
+    pub state: OutputState,
+}
+
+impl<T: Clone> InOut<T> {
+    pub fn new(value: T) -> InOut<T> {
+        InOut {
+            value,
             state: OutputState::NoChange,
         }
     }
@@ -51,23 +151,118 @@ impl<T: Clone> Output<T> {
         self.value = value;
         self.state = OutputState::Updated;
     }
+
+    pub fn update(&mut self, f: impl FnOnce(&mut T)) {
+        f(&mut self.value);
+        self.state = OutputState::Updated;
+    }
+
+    // Returns the new value if the callback updated the field.
+    pub fn into_update(self) -> Option<T> {
+        match self.state {
+            OutputState::NoChange => None,
+            OutputState::Updated => Some(self.value),
+        }
+    }
+}
+
+impl<T: Clone> std::ops::Deref for InOut<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Clone> std::ops::DerefMut for InOut<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.state = OutputState::Updated;
+        &mut self.value
+    }
 }
 
+// Function running a callback against the state, see `StateCallback::cb`.
+pub type CallbackFn<V, S> = std::sync::Arc<dyn Fn(&mut S) -> Result<Vec<V>, String> + Send + Sync>;
+
 #[derive(Clone)]
 pub struct StateCallback<I, V, S> {
-    pub name: &'static str,
-    pub cb: fn(&mut S) -> Vec<V>,
+    // Callbacks of nested states are named after the field embedding them, e.g. `filters.apply`.
+    pub name: std::borrow::Cow<'static, str>,
+    // Module the callback is defined in, telling apart callbacks with the same name.
+    pub module_path: &'static str,
+    // Returns the output updates, or an error message skipping the callbacks depending on them.
+    pub cb: CallbackFn<V, S>,
     pub inputs: Vec<I>,
     pub outputs: Vec<I>,
+    // Fields read and updated in place through InOut<T>.
+    pub in_outs: Vec<I>,
+    pub guard: Option<Guard>,
 }
 
 impl<I, S, V> StateCallback<I, V, S> {
-    pub fn new(name: &'static str, cb: fn(&mut S) -> Vec<V>, inputs: Vec<I>, outputs: Vec<I>) -> Self {
+    pub fn new(
+        name: &'static str,
+        cb: fn(&mut S) -> Result<Vec<V>, String>,
+        inputs: Vec<I>,
+        outputs: Vec<I>,
+    ) -> Self
+    where
+        V: 'static,
+        S: 'static,
+    {
         Self {
-            name,
-            cb,
+            name: std::borrow::Cow::Borrowed(name),
+            module_path: "",
+            cb: std::sync::Arc::new(cb),
             inputs,
             outputs,
+            in_outs: Vec::new(),
+            guard: None,
+        }
+    }
+
+    pub fn with_guard(mut self, guard: Guard) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    pub fn with_in_outs(mut self, in_outs: Vec<I>) -> Self {
+        self.in_outs = in_outs;
+        self
+    }
+
+    pub fn with_module_path(mut self, module_path: &'static str) -> Self {
+        self.module_path = module_path;
+        self
+    }
+
+    // Turns a callback of a nested state into a callback of the parent state embedding it in
+    // `field`. The callback runs on the nested state and its identifiers and values are wrapped
+    // into the parent's variants for that field.
+    pub fn nest<PI, PV, P>(
+        self,
+        field: &'static str,
+        get_field: fn(&mut P) -> &mut S,
+        identifier: fn(I) -> PI,
+        value: fn(V) -> PV,
+    ) -> StateCallback<PI, PV, P>
+    where
+        V: 'static,
+        S: 'static,
+        PV: 'static,
+        P: 'static,
+    {
+        let cb = self.cb;
+        StateCallback {
+            name: std::borrow::Cow::Owned(format!("{}.{}", field, self.name)),
+            module_path: self.module_path,
+            cb: std::sync::Arc::new(move |state: &mut P| {
+                Ok(cb(get_field(state))?.into_iter().map(value).collect())
+            }),
+            inputs: self.inputs.into_iter().map(identifier).collect(),
+            outputs: self.outputs.into_iter().map(identifier).collect(),
+            in_outs: self.in_outs.into_iter().map(identifier).collect(),
+            guard: self.guard,
         }
     }
 }
@@ -77,16 +272,15 @@ impl<I, S, V> std::hash::Hash for StateCallback<I, V, S> {
     where
         H: std::hash::Hasher,
     {
-        let pointer = self.cb as *const ();
+        let pointer = std::sync::Arc::as_ptr(&self.cb) as *const ();
         pointer.hash(state);
-        state.finish();
     }
 }
 
 impl<I, V, S> PartialEq for StateCallback<I, V, S> {
     fn eq(&self, other: &StateCallback<I, V, S>) -> bool {
-        let self_pointer = self.cb as *const ();
-        let other_pointer = other.cb as *const ();
+        let self_pointer = std::sync::Arc::as_ptr(&self.cb) as *const ();
+        let other_pointer = std::sync::Arc::as_ptr(&other.cb) as *const ();
         return self_pointer == other_pointer;
     }
 }
@@ -97,21 +291,272 @@ pub struct StateCallbackWithId<I, V, S> {
     pub callback: StateCallback<I, V, S>,
 }
 
+// Forwards the input updates of a nested state's context to the context of the parent state, which
+// owns the executor and the server callback they are processed by.
+pub struct UpdateForwarder<V> {
+    forward: Option<std::rc::Rc<dyn Fn(Vec<V>)>>,
+    // Whether the parent is waiting for the server, which is also the loading state of the nested
+    // context.
+    loading: Option<leptos::Signal<bool>>,
+}
+
+impl<V> UpdateForwarder<V> {
+    pub fn none() -> UpdateForwarder<V> {
+        UpdateForwarder {
+            forward: None,
+            loading: None,
+        }
+    }
+
+    pub fn new(forward: impl Fn(Vec<V>) + 'static) -> UpdateForwarder<V> {
+        UpdateForwarder {
+            forward: Some(std::rc::Rc::new(forward)),
+            loading: None,
+        }
+    }
+
+    pub fn with_loading(mut self, loading: leptos::Signal<bool>) -> Self {
+        self.loading = Some(loading);
+        return self;
+    }
+
+    pub fn loading(&self) -> Option<leptos::Signal<bool>> {
+        return self.loading;
+    }
+
+    // Returns the updates back if there's no parent to forward them to.
+    pub fn forward(&self, updates: Vec<V>) -> Option<Vec<V>> {
+        match self.forward {
+            Some(ref forward) => {
+                forward(updates);
+                None
+            }
+            None => Some(updates),
+        }
+    }
+}
+
+impl<V> Clone for UpdateForwarder<V> {
+    fn clone(&self) -> Self {
+        UpdateForwarder {
+            forward: self.forward.clone(),
+            loading: self.loading,
+        }
+    }
+}
+
+impl<V> std::fmt::Debug for UpdateForwarder<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpdateForwarder")
+            .field("nested", &self.forward.is_some())
+            .finish()
+    }
+}
+
 pub trait StateTypes {
     type Identifier;
     type Value;
     type CallbackInfo;
     type Context;
+    // Type providing an accessor function for each field, named after the field.
+    type FieldAccess: Default;
+    // States with #[dust(embedded)], which have no executor or endpoint of their own.
+    const EMBEDDED: bool = false;
+}
+
+// Implemented by non-generic DustState structs, whose callbacks register themselves from
+// `dust_define_callback` through a link-time collected `Registration`.
+pub trait CallbackRegistry: StateTypes {
+    type Registration;
 }
 
+// Returns the FieldAccess of the state reached through `get_field`. Callbacks bound to field paths
+// use it to build the identifiers and values of nested fields.
+pub fn field_access_of<S, C: StateTypes>(_get_field: impl Fn(&S) -> &C) -> C::FieldAccess {
+    C::FieldAccess::default()
+}
+
+// Server callback endpoint of a state instance. DustState registers one per instance so that
+// states claiming the same path are reported when the server starts, instead of one of them
+// silently receiving the requests of the other.
+pub struct ServerCallbackEndpoint {
+    pub path: &'static str,
+    pub state: &'static str,
+    // Builds the executor of the instance, so that callbacks which can't be ordered are reported
+    // at startup rather than on the first request.
+    pub check: fn() -> Result<(), String>,
+}
+
+pub fn check_executor<S: StateInstance>() -> Result<(), String> {
+    return S::executor().map(|_| ());
+}
+
+inventory::collect!(ServerCallbackEndpoint);
+
+// Returns an error naming the states that share a server callback endpoint or whose callbacks
+// can't be ordered, if any.
+pub fn check_endpoints() -> Result<(), String> {
+    let mut states_by_path: HashMap<&'static str, &'static str> = HashMap::new();
+    for endpoint in inventory::iter::<ServerCallbackEndpoint> {
+        if let Some(other) = states_by_path.insert(endpoint.path, endpoint.state) {
+            return Err(format!(
+                "{} and {} both use the server callback endpoint {}, give one of them another \
+                #[dust(endpoint = \"...\")]",
+                other, endpoint.state, endpoint.path
+            ));
+        }
+        (endpoint.check)().map_err(|error| format!("{}: {}", endpoint.state, error))?;
+    }
+    return Ok(());
+}
+
+// Implemented by DustState structs for each concrete instance (a single one for non-generic
+// structs), providing the executor and the server callback endpoint for that instance.
+pub trait StateInstance: StateTypes + Sized + 'static {
+    // Fails if the callbacks of the state can't be ordered, see `Executor::init_callbacks`.
+    fn executor() -> Result<&'static Executor<Self::Identifier, Self::Value, Self>, String>;
+
+    fn server_callback(
+        input_updates: Vec<Self::Value>,
+        required_state: Vec<Self::Value>,
+        signed_state: Vec<SignedValue>,
+    ) -> ServerCallbackFuture<Self::Value>;
+}
+
+// Response of the server callback endpoint of a state, see `StateInstance::server_callback`.
+pub type ServerCallbackFuture<V> = std::pin::Pin<
+    Box<dyn std::future::Future<Output = Result<UpdateResponse<V>, leptos::ServerFnError>>>,
+>;
+
 impl<I, V, S> Eq for StateCallback<I, V, S> {}
 
 pub trait ValueToIdentifier<I> {
     fn to_identifier(&self) -> I;
 }
 
+// Checks a value against the constraints declared on its field, e.g. #[dust(range(0..=100))].
+pub trait ValidateValue {
+    fn validate(&self) -> Result<(), UpdateError>;
+}
+
+// Length limited by #[dust(max_len = ...)]: characters for strings, elements for collections.
+pub trait Length {
+    fn length(&self) -> usize;
+}
+
+impl Length for String {
+    fn length(&self) -> usize {
+        return self.chars().count();
+    }
+}
+
+impl<T> Length for Vec<T> {
+    fn length(&self) -> usize {
+        return self.len();
+    }
+}
+
+impl<T> Length for std::collections::VecDeque<T> {
+    fn length(&self) -> usize {
+        return self.len();
+    }
+}
+
+impl<T> Length for HashSet<T> {
+    fn length(&self) -> usize {
+        return self.len();
+    }
+}
+
+impl<K, T> Length for HashMap<K, T> {
+    fn length(&self) -> usize {
+        return self.len();
+    }
+}
+
+impl<T> Length for std::collections::BTreeSet<T> {
+    fn length(&self) -> usize {
+        return self.len();
+    }
+}
+
+impl<K, T> Length for std::collections::BTreeMap<K, T> {
+    fn length(&self) -> usize {
+        return self.len();
+    }
+}
+
 pub trait ApplyUpdates<V> {
-    fn apply_updates(&mut self, updates: &Vec<V>);
+    fn apply_updates(&mut self, updates: &[V]);
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum UpdateError {
+    // A callback in the execution plan had its guard rejecting the request.
+    Unauthorized {
+        callback: String,
+        message: String,
+    },
+    // The client sent an input update for a field that isn't a callback input, or that is
+    // computed on the server as a callback output.
+    InvalidInputUpdate(String),
+    // The client sent state that isn't required by the execution plan of its input updates, or
+    // sent a callback output without the signature it was issued with.
+    InvalidRequiredState(String),
+    // A signed value sent by the client doesn't match its signature.
+    InvalidSignature,
+    // A value sent by the client violates the constraints declared on its field.
+    InvalidValue {
+        field: String,
+        message: String,
+    },
+    // A callback returned an error.
+    CallbackFailed {
+        callback: String,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::Unauthorized { callback, message } => {
+                write!(f, "callback {} unauthorized: {}", callback, message)
+            }
+            UpdateError::InvalidInputUpdate(identifier) => {
+                write!(f, "{} is not accepted as an input update", identifier)
+            }
+            UpdateError::InvalidRequiredState(identifier) => {
+                write!(f, "{} is not part of the required state", identifier)
+            }
+            UpdateError::InvalidSignature => write!(f, "invalid signed value"),
+            UpdateError::InvalidValue { field, message } => {
+                write!(f, "invalid value for {}: {}", field, message)
+            }
+            UpdateError::CallbackFailed { callback, message } => {
+                write!(f, "callback {} failed: {}", callback, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
+
+/// Result of a server callback: the output updates, each with its signed form, and the errors of
+/// the callbacks that were skipped while the rest of the execution plan ran.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct UpdateResponse<V> {
+    pub updates: Vec<(V, SignedValue)>,
+    pub errors: Vec<UpdateError>,
+}
+
+impl<V> UpdateResponse<V> {
+    pub fn from_errors(errors: Vec<UpdateError>) -> UpdateResponse<V> {
+        UpdateResponse {
+            updates: Vec::new(),
+            errors,
+        }
+    }
 }
 
 pub struct Executor<I, V, S> {
@@ -121,12 +566,27 @@ pub struct Executor<I, V, S> {
     callback_to_dependants: HashMap<usize, Vec<usize>>,
     // Maps callback ids to their index in the topological sort.
     callback_to_topological_rank: HashMap<usize, usize>,
+    // Maps identifiers to the ids of the callbacks that output them.
+    output_to_callbacks: HashMap<I, Vec<usize>>,
+    // Identifiers of fields that never leave the server.
+    server_only: HashSet<I>,
+}
+
+impl<I, V, S> Default for Executor<I, V, S>
+where
+    I: Hash + PartialEq + Eq + Clone + Copy + std::fmt::Debug,
+    V: Clone + std::fmt::Debug + ValueToIdentifier<I> + ValidateValue,
+    S: Clone + Default + ApplyUpdates<V>,
+{
+    fn default() -> Self {
+        return Self::new();
+    }
 }
 
 impl<I, V, S> Executor<I, V, S>
 where
-    I: Hash + PartialEq + Eq + Clone + Copy,
-    V: Clone + std::fmt::Debug + ValueToIdentifier<I>,
+    I: Hash + PartialEq + Eq + Clone + Copy + std::fmt::Debug,
+    V: Clone + std::fmt::Debug + ValueToIdentifier<I> + ValidateValue,
     S: Clone + Default + ApplyUpdates<V>,
 {
     pub fn new() -> Executor<I, V, S> {
@@ -135,6 +595,8 @@ where
             input_to_callbacks: HashMap::new(),
             callback_to_dependants: HashMap::new(),
             callback_to_topological_rank: HashMap::new(),
+            output_to_callbacks: HashMap::new(),
+            server_only: HashSet::new(),
         };
         return app;
     }
@@ -143,16 +605,20 @@ where
         let id = self.callbacks.len();
 
         self.callbacks.push(StateCallbackWithId {
-            id: id,
-            callback: callback,
+            id,
+            callback,
         });
     }
 
-    pub fn init_callbacks(&mut self) {
+    pub fn register_server_only(&mut self, identifier: I) {
+        self.server_only.insert(identifier);
+    }
+
+    pub fn init_callbacks(&mut self) -> Result<(), String> {
         for cb in self.callbacks.iter() {
             for input in cb.callback.inputs.iter() {
                 if !self.input_to_callbacks.contains_key(input) {
-                    self.input_to_callbacks.insert(input.clone(), Vec::new());
+                    self.input_to_callbacks.insert(*input, Vec::new());
                 }
 
                 let v = self.input_to_callbacks.get_mut(input).unwrap();
@@ -160,13 +626,12 @@ where
             }
         }
 
-        let mut callback_names: HashMap<usize, &'static str> = HashMap::new();
         for cb in self.callbacks.iter() {
-            callback_names.insert(cb.id, cb.callback.name);
             self.callback_to_dependants.insert(cb.id, Vec::new());
             let edges = self.callback_to_dependants.get_mut(&cb.id).unwrap();
 
-            for output in cb.callback.outputs.iter() {
+            for output in cb.callback.outputs.iter().chain(cb.callback.in_outs.iter()) {
+                self.output_to_callbacks.entry(*output).or_default().push(cb.id);
                 if let Some(deps) = self.input_to_callbacks.get(output) {
                     for dep in deps.iter() {
                         edges.push(*dep);
@@ -175,6 +640,21 @@ where
             }
         }
 
+        // Fields updated in place aren't ordered against callbacks computing them from scratch, so
+        // a field can't be both.
+        for cb in self.callbacks.iter() {
+            for in_out in cb.callback.in_outs.iter() {
+                for other in self.callbacks.iter() {
+                    if other.callback.outputs.contains(in_out) {
+                        return Err(format!(
+                            "Field {:?} is updated in place by {} and is an output of {}",
+                            in_out, cb.callback.name, other.callback.name
+                        ));
+                    }
+                }
+            }
+        }
+
         let mut temp_marks: HashSet<usize> = HashSet::new();
         let mut perm_marks: HashSet<usize> = HashSet::new();
         let mut cycle: Vec<usize> = Vec::new();
@@ -182,7 +662,6 @@ where
 
         fn visit(
             id: usize,
-            callback_names: &HashMap<usize, &'static str>,
             callback_to_dependants: &HashMap<usize, Vec<usize>>,
             temp_marks: &mut HashSet<usize>,
             perm_marks: &mut HashSet<usize>,
@@ -201,7 +680,6 @@ where
             for dep in callback_to_dependants.get(&id).unwrap().iter() {
                 if visit(
                     *dep,
-                    callback_names,
                     callback_to_dependants,
                     temp_marks,
                     perm_marks,
@@ -222,31 +700,37 @@ where
         }
 
         for cb in self.callbacks.iter() {
-            if !perm_marks.contains(&cb.id) {
-                if visit(
+            if !perm_marks.contains(&cb.id)
+                && visit(
                     cb.id,
-                    &callback_names,
                     &self.callback_to_dependants,
                     &mut temp_marks,
                     &mut perm_marks,
                     &mut cycle,
                     &mut topological_order,
-                ) {
-                    let cycle_description = cycle
-                        .iter()
-                        .rev()
-                        .map(|id| self.callbacks[*id].callback.name)
-                        .collect::<Vec<&str>>()
-                        .join(" -> ");
-                    panic!("Found callback cycle: {}", cycle_description);
+                )
+            {
+                let cycle_description = cycle
+                    .iter()
+                    .rev()
+                    .map(|id| self.callbacks[*id].callback.name.as_ref())
+                    .collect::<Vec<&str>>()
+                    .join(" -> ");
+                if cycle.len() == 2 {
+                    return Err(format!(
+                        "Found callback cycle: {} (use InOut<T> for fields a callback \
+                        updates in place)",
+                        cycle_description
+                    ));
                 }
+                return Err(format!("Found callback cycle: {}", cycle_description));
             }
         }
 
         topological_order.reverse();
         let topological_order_description = topological_order
             .iter()
-            .map(|id| self.callbacks[*id].callback.name)
+            .map(|id| self.callbacks[*id].callback.name.as_ref())
             .collect::<Vec<&str>>()
             .join(" -> ");
         log!(
@@ -257,9 +741,10 @@ where
         for (rank, id) in topological_order.iter().enumerate() {
             self.callback_to_topological_rank.insert(*id, rank);
         }
+        return Ok(());
     }
 
-    pub fn get_execution_plan(&self, updated_inputs: &Vec<I>) -> Vec<usize> {
+    pub fn get_execution_plan(&self, updated_inputs: &[I]) -> Vec<usize> {
         let mut execution_plan: Vec<usize> = Vec::new();
         let mut visited_cb_ids: HashSet<usize> = HashSet::new();
 
@@ -281,7 +766,10 @@ where
         }
 
         for input in updated_inputs.iter() {
-            for id in self.input_to_callbacks.get(input).unwrap().iter() {
+            let Some(ids) = self.input_to_callbacks.get(input) else {
+                continue;
+            };
+            for id in ids.iter() {
                 visit(
                     *id,
                     &self.callback_to_dependants,
@@ -291,26 +779,58 @@ where
             }
         }
 
-        execution_plan.sort_by_key(|id| self.callback_to_topological_rank.get(id).unwrap());
+        // Server-only fields can't be provided by the client, so the callbacks computing the ones
+        // required by the plan need to run as well.
+        loop {
+            execution_plan.sort_by_key(|id| self.callback_to_topological_rank.get(id).unwrap());
+            let mut extended = false;
+            for input in self.get_all_required_state(updated_inputs, &execution_plan) {
+                if !self.server_only.contains(&input) {
+                    continue;
+                }
+                if let Some(ids) = self.output_to_callbacks.get(&input) {
+                    for id in ids.iter() {
+                        if visited_cb_ids.insert(*id) {
+                            execution_plan.push(*id);
+                            extended = true;
+                        }
+                    }
+                }
+            }
+            if !extended {
+                break;
+            }
+        }
         return execution_plan;
     }
 
     pub fn get_required_state(
         &self,
-        updated_inputs: &Vec<I>,
-        execution_plan: &Vec<usize>,
+        updated_inputs: &[I],
+        execution_plan: &[usize],
+    ) -> HashSet<I> {
+        let mut required_state = self.get_all_required_state(updated_inputs, execution_plan);
+        required_state.retain(|input| !self.server_only.contains(input));
+        return required_state;
+    }
+
+    // Same as get_required_state, but including server-only fields.
+    fn get_all_required_state(
+        &self,
+        updated_inputs: &[I],
+        execution_plan: &[usize],
     ) -> HashSet<I> {
         let mut available_inputs: HashSet<I> = HashSet::from_iter(updated_inputs.iter().cloned());
         let mut required_state: HashSet<I> = HashSet::new();
 
         for id in execution_plan.iter() {
             let callback = &self.callbacks[*id].callback;
-            for input in callback.inputs.iter() {
+            for input in callback.inputs.iter().chain(callback.in_outs.iter()) {
                 if !available_inputs.contains(input) {
                     required_state.insert(*input);
                 }
             }
-            for output in callback.outputs.iter() {
+            for output in callback.outputs.iter().chain(callback.in_outs.iter()) {
                 available_inputs.insert(*output);
             }
         }
@@ -322,7 +842,7 @@ where
         let mut required_inputs: HashSet<I> = HashSet::new();
         for cb in self.callbacks.iter() {
             for input in cb.callback.inputs.iter() {
-                required_inputs.insert(input.clone());
+                required_inputs.insert(*input);
             }
         }
 
@@ -335,27 +855,423 @@ where
                 }
             }
         }
+        required_inputs.retain(|input| !self.server_only.contains(input));
         return required_inputs;
     }
 
-    pub fn process_updates(&self, input_updates: Vec<V>, required_state: Vec<V>) -> Vec<V> {
+    pub fn is_output(&self, identifier: &I) -> bool {
+        return self.output_to_callbacks.contains_key(identifier);
+    }
+
+    // Checks that the updates received from a client are consistent with the callback graph: input
+    // updates can only target fields that are inputs (and never outputs) of callbacks, and the
+    // required state must be a subset of what the execution plan needs. Callback outputs can only
+    // be part of the required state through signed values. Values set by the client also need to
+    // satisfy the constraints declared on their fields.
+    pub fn validate_updates(
+        &self,
+        input_updates: &[V],
+        required_state: &[V],
+        signed_required_state: &[V],
+    ) -> Result<(), UpdateError> {
+        for value in input_updates.iter().chain(required_state.iter()) {
+            value.validate()?;
+        }
+
+        let updated_inputs: Vec<I> = input_updates.iter().map(|v| v.to_identifier()).collect();
+        for input in updated_inputs.iter() {
+            if !self.input_to_callbacks.contains_key(input)
+                || self.is_output(input)
+                || self.server_only.contains(input)
+            {
+                return Err(UpdateError::InvalidInputUpdate(format!("{:?}", input)));
+            }
+        }
+
+        let execution_plan = self.get_execution_plan(&updated_inputs);
+        let expected_required_state = self.get_required_state(&updated_inputs, &execution_plan);
+        for value in required_state.iter() {
+            let identifier = value.to_identifier();
+            if !expected_required_state.contains(&identifier) || self.is_output(&identifier) {
+                return Err(UpdateError::InvalidRequiredState(format!("{:?}", identifier)));
+            }
+        }
+        for value in signed_required_state.iter() {
+            let identifier = value.to_identifier();
+            if !expected_required_state.contains(&identifier) {
+                return Err(UpdateError::InvalidRequiredState(format!("{:?}", identifier)));
+            }
+        }
+        return Ok(());
+    }
+
+    // Runs the callbacks triggered by the input updates and returns their output updates, each
+    // with its signed form. Outputs missing from the signed state keep their default value.
+    // Callbacks whose guard rejects the request, or that return an error, are skipped along with
+    // the callbacks depending on their outputs, and reported in the response errors.
+    #[cfg(feature = "ssr")]
+    pub fn process_updates(
+        &self,
+        request: &http::request::Parts,
+        input_updates: Vec<V>,
+        required_state: Vec<V>,
+        signed_state: Vec<SignedValue>,
+    ) -> Result<UpdateResponse<V>, UpdateError>
+    where
+        V: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        // Values are signed for their state, so that they can't be sent to another state's endpoint.
+        let context = std::any::type_name::<S>();
+        let mut signed_required_state: Vec<V> = Vec::new();
+        for signed in signed_state.iter() {
+            match signing::verify(context, signed) {
+                Some(value) => signed_required_state.push(value),
+                None => return Err(UpdateError::InvalidSignature),
+            }
+        }
+        self.validate_updates(&input_updates, &required_state, &signed_required_state)?;
+
         let mut state = S::default();
         state.apply_updates(&required_state);
+        state.apply_updates(&signed_required_state);
         state.apply_updates(&input_updates);
 
-        let updated_inputs = input_updates.iter().map(|v| v.to_identifier()).collect();
+        let updated_inputs: Vec<I> = input_updates.iter().map(|v| v.to_identifier()).collect();
         let execution_plan = self.get_execution_plan(&updated_inputs);
 
+        let mut errors: Vec<UpdateError> = Vec::new();
+        // Callbacks reading outputs of skipped callbacks, which would see stale values.
+        let mut skipped: HashSet<usize> = HashSet::new();
         let mut output_updates: Vec<V> = Vec::new();
         for id in execution_plan.iter() {
             let callback = &self.callbacks[*id].callback;
+            let authorized = match &callback.guard {
+                Some(guard) => guard.check(request),
+                None => Ok(()),
+            };
+            if let Err(message) = authorized {
+                errors.push(UpdateError::Unauthorized {
+                    callback: callback.name.to_string(),
+                    message,
+                });
+                skipped.insert(*id);
+            }
+            if skipped.contains(id) {
+                // The plan is topologically sorted, so dependants are visited after this one.
+                skipped.extend(self.callback_to_dependants.get(id).unwrap().iter().copied());
+                continue;
+            }
 
-            let mut new_updates = (callback.cb)(&mut state);
-            state.apply_updates(&new_updates);
-            output_updates.append(&mut new_updates);
+            match (callback.cb)(&mut state) {
+                Ok(mut new_updates) => {
+                    state.apply_updates(&new_updates);
+                    output_updates.append(&mut new_updates);
+                }
+                Err(message) => {
+                    // Like a rejecting guard, the callbacks depending on it are skipped.
+                    errors.push(UpdateError::CallbackFailed {
+                        callback: callback.name.to_string(),
+                        message,
+                    });
+                    skipped.extend(self.callback_to_dependants.get(id).unwrap().iter().copied());
+                }
+            }
         }
 
         println!("output_updates: {:?}", output_updates);
-        return output_updates;
+        let updates = output_updates
+            .into_iter()
+            .filter(|value| !self.server_only.contains(&value.to_identifier()))
+            .map(|value| {
+                let signed = signing::sign(context, &value);
+                (value, signed)
+            })
+            .collect();
+        return Ok(UpdateResponse { updates, errors });
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+    enum Identifier {
+        A,
+        C,
+        Secret,
+        D,
+        Total,
+        Count,
+    }
+
+    #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    enum Value {
+        A(u32),
+        C(u32),
+        Secret(u32),
+        D(u32),
+        Total(u32),
+        Count(u32),
+    }
+
+    impl ValueToIdentifier<Identifier> for Value {
+        fn to_identifier(&self) -> Identifier {
+            match self {
+                Value::A(_) => Identifier::A,
+                Value::C(_) => Identifier::C,
+                Value::Secret(_) => Identifier::Secret,
+                Value::D(_) => Identifier::D,
+                Value::Total(_) => Identifier::Total,
+                Value::Count(_) => Identifier::Count,
+            }
+        }
+    }
+
+    impl ValidateValue for Value {
+        fn validate(&self) -> Result<(), UpdateError> {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct State {
+        a: u32,
+        c: u32,
+        secret: u32,
+        d: u32,
+        total: u32,
+        count: u32,
+    }
+
+    impl ApplyUpdates<Value> for State {
+        fn apply_updates(&mut self, updates: &[Value]) {
+            for update in updates.iter() {
+                match update {
+                    Value::A(value) => self.a = *value,
+                    Value::C(value) => self.c = *value,
+                    Value::Secret(value) => self.secret = *value,
+                    Value::D(value) => self.d = *value,
+                    Value::Total(value) => self.total = *value,
+                    Value::Count(value) => self.count = *value,
+                }
+            }
+        }
+    }
+
+    // secret = a * 10 (server only), d = c + secret, total += d (in place). The callbacks are
+    // registered in reverse order so that running them in registration order would be wrong.
+    fn executor() -> Executor<Identifier, Value, State> {
+        let mut executor = Executor::new();
+        executor.register_server_only(Identifier::Secret);
+        executor.register_callback(
+            StateCallback::new(
+                "add_total",
+                |state: &mut State| Ok(vec![Value::Total(state.total + state.d)]),
+                vec![Identifier::D],
+                vec![],
+            )
+            .with_in_outs(vec![Identifier::Total]),
+        );
+        executor.register_callback(StateCallback::new(
+            "use_secret",
+            |state: &mut State| Ok(vec![Value::D(state.c + state.secret)]),
+            vec![Identifier::C, Identifier::Secret],
+            vec![Identifier::D],
+        ));
+        executor.register_callback(StateCallback::new(
+            "compute_secret",
+            |state: &mut State| Ok(vec![Value::Secret(state.a * 10)]),
+            vec![Identifier::A],
+            vec![Identifier::Secret],
+        ));
+        executor.init_callbacks().unwrap();
+        executor
+    }
+
+    fn sign(value: &Value) -> SignedValue {
+        signing::sign(std::any::type_name::<State>(), value)
+    }
+
+    fn request() -> http::request::Parts {
+        http::Request::new(()).into_parts().0
+    }
+
+    #[test]
+    fn tampered_signed_value_is_rejected() {
+        let executor = executor();
+        // The bytes of another value, kept with the signature of the original one.
+        let mut signed = sign(&Value::Total(5));
+        signed.bytes.clear();
+        ciborium::into_writer(&Value::Total(500), &mut signed.bytes).unwrap();
+
+        let result = executor.process_updates(
+            &request(),
+            vec![Value::C(1)],
+            vec![Value::A(1)],
+            vec![signed],
+        );
+        assert!(matches!(result, Err(UpdateError::InvalidSignature)));
+    }
+
+    #[test]
+    fn value_signed_for_another_state_is_rejected() {
+        let executor = executor();
+        let signed = signing::sign("other_module::State", &Value::Total(5));
+
+        let result = executor.process_updates(
+            &request(),
+            vec![Value::C(1)],
+            vec![Value::A(1)],
+            vec![signed],
+        );
+        assert!(matches!(result, Err(UpdateError::InvalidSignature)));
+    }
+
+    #[test]
+    fn unsigned_server_only_value_is_rejected() {
+        let executor = executor();
+        let signed = sign(&Value::Total(5));
+
+        let result = executor.process_updates(
+            &request(),
+            vec![Value::C(1)],
+            vec![Value::A(1), Value::Secret(3)],
+            vec![signed],
+        );
+        assert!(matches!(result, Err(UpdateError::InvalidRequiredState(_))));
+
+        let result = executor.process_updates(&request(), vec![Value::Secret(3)], vec![], vec![]);
+        assert!(matches!(result, Err(UpdateError::InvalidInputUpdate(_))));
+    }
+
+    #[test]
+    fn plan_is_extended_with_callbacks_computing_server_only_inputs() {
+        let executor = executor();
+        let names = |plan: &Vec<usize>| {
+            plan.iter()
+                .map(|id| executor.callbacks[*id].callback.name.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        let plan = executor.get_execution_plan(&[Identifier::C]);
+        assert_eq!(names(&plan), vec!["compute_secret", "use_secret", "add_total"]);
+
+        let required_state = executor.get_required_state(&[Identifier::C], &plan);
+        assert_eq!(required_state, HashSet::from([Identifier::A, Identifier::Total]));
+    }
+
+    #[test]
+    fn in_out_callback_runs_once_in_topological_order() {
+        let executor = executor();
+
+        let response = executor
+            .process_updates(
+                &request(),
+                vec![Value::C(2)],
+                vec![Value::A(1)],
+                vec![sign(&Value::Total(5))],
+            )
+            .unwrap();
+        let updates: Vec<Value> = response.updates.into_iter().map(|(value, _)| value).collect();
+        // The server only secret isn't sent back, total is updated once from d = 2 + 10.
+        assert_eq!(updates, vec![Value::D(12), Value::Total(17)]);
+        assert!(response.errors.is_empty());
+    }
+
+    // The executor above with compute_secret restricted to admins, and count = c + 1 computed
+    // beside it without a guard.
+    fn guarded_executor() -> Executor<Identifier, Value, State> {
+        let mut executor = Executor::new();
+        executor.register_server_only(Identifier::Secret);
+        executor.register_callback(
+            StateCallback::new(
+                "add_total",
+                |state: &mut State| Ok(vec![Value::Total(state.total + state.d)]),
+                vec![Identifier::D],
+                vec![],
+            )
+            .with_in_outs(vec![Identifier::Total]),
+        );
+        executor.register_callback(StateCallback::new(
+            "use_secret",
+            |state: &mut State| Ok(vec![Value::D(state.c + state.secret)]),
+            vec![Identifier::C, Identifier::Secret],
+            vec![Identifier::D],
+        ));
+        executor.register_callback(
+            StateCallback::new(
+                "compute_secret",
+                |state: &mut State| Ok(vec![Value::Secret(state.a * 10)]),
+                vec![Identifier::A],
+                vec![Identifier::Secret],
+            )
+            .with_guard(require_role("admin")),
+        );
+        executor.register_callback(StateCallback::new(
+            "count",
+            |state: &mut State| Ok(vec![Value::Count(state.c + 1)]),
+            vec![Identifier::C],
+            vec![Identifier::Count],
+        ));
+        executor.init_callbacks().unwrap();
+        executor
+    }
+
+    fn request_with_roles(roles: &[&str]) -> http::request::Parts {
+        let mut request = request();
+        request
+            .extensions
+            .insert(Roles(roles.iter().map(|role| role.to_string()).collect()));
+        request
+    }
+
+    fn process_guarded(request: &http::request::Parts) -> UpdateResponse<Value> {
+        guarded_executor()
+            .process_updates(
+                request,
+                vec![Value::C(2)],
+                vec![Value::A(1)],
+                vec![sign(&Value::Total(5))],
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn rejected_guard_skips_the_callback_and_its_dependants() {
+        let response = process_guarded(&request_with_roles(&["viewer"]));
+        let updates: Vec<Value> = response.updates.into_iter().map(|(value, _)| value).collect();
+        // use_secret and add_total depend on the secret and don't run, count still does.
+        assert_eq!(updates, vec![Value::Count(3)]);
+        assert_eq!(response.errors.len(), 1);
+        assert!(matches!(
+            &response.errors[0],
+            UpdateError::Unauthorized { callback, .. } if callback.contains("compute_secret")
+        ));
+    }
+
+    #[test]
+    fn accepted_guard_runs_the_whole_plan() {
+        let response = process_guarded(&request_with_roles(&["viewer", "admin"]));
+        let mut updates: Vec<Value> =
+            response.updates.into_iter().map(|(value, _)| value).collect();
+        updates.sort_by_key(|value| format!("{:?}", value));
+        assert_eq!(updates, vec![Value::Count(3), Value::D(12), Value::Total(17)]);
+        assert!(response.errors.is_empty());
+    }
+
+    #[test]
+    fn require_role_checks_the_request_roles() {
+        let guard = require_role("admin");
+        assert!(guard.check(&request_with_roles(&["admin"])).is_ok());
+        assert_eq!(
+            guard.check(&request_with_roles(&["viewer"])),
+            Err("the admin role is required".to_string())
+        );
+        // Requests without roles weren't authenticated.
+        assert_eq!(
+            guard.check(&request()),
+            Err("the request is not authenticated".to_string())
+        );
     }
 }
@@ -0,0 +1,82 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Resources registered when the server starts (see serve::serve_with_resources).
+static REGISTERED_RESOURCES: once_cell::sync::OnceCell<Resources> =
+    once_cell::sync::OnceCell::new();
+
+/// Set of shared server resources (database pools, config, caches, ...), keyed by type.
+#[derive(Default)]
+pub struct Resources {
+    entries: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Resources {
+    pub fn new() -> Resources {
+        Resources::default()
+    }
+
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.entries.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn with<T: Send + Sync + 'static>(mut self, value: T) -> Resources {
+        self.insert(value);
+        return self;
+    }
+
+    fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.entries
+            .get(&TypeId::of::<T>())
+            .map(|entry| entry.clone().downcast::<T>().unwrap())
+    }
+}
+
+/// Makes the resources available to callbacks. Can only be called once per process, later calls
+/// return the resources back.
+pub fn register_resources(resources: Resources) -> Result<(), Resources> {
+    return REGISTERED_RESOURCES.set(resources);
+}
+
+/// Callback parameter giving access to a resource registered at startup. It's resolved on the
+/// server when the callback runs and is never part of the state.
+pub struct Resource<T> {
+    value: Arc<T>,
+}
+
+impl<T: Send + Sync + 'static> Resource<T> {
+    pub fn get() -> Option<Resource<T>> {
+        REGISTERED_RESOURCES
+            .get()
+            .and_then(|resources| resources.get::<T>())
+            .map(|value| Resource { value })
+    }
+
+    // Like get, with an error naming the missing resource. Callbacks fail with it instead of
+    // panicking the server.
+    pub fn require() -> Result<Resource<T>, String> {
+        return Resource::get().ok_or_else(|| {
+            format!(
+                "Resource of type {} was not registered",
+                std::any::type_name::<T>()
+            )
+        });
+    }
+}
+
+impl<T> Clone for Resource<T> {
+    fn clone(&self) -> Self {
+        Resource {
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<T> std::ops::Deref for Resource<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
@@ -4,9 +4,28 @@ pub async fn serve<IV>(
     app_fn: impl Fn() -> IV + 'static + Clone + Send,
 ) where
 IV: leptos::IntoView + 'static,
+{
+    serve_with_resources(app_fn, crate::Resources::new()).await;
+}
+
+#[cfg(feature = "ssr")]
+pub async fn serve_with_resources<IV>(
+    app_fn: impl Fn() -> IV + 'static + Clone + Send,
+    resources: crate::Resources,
+) where
+IV: leptos::IntoView + 'static,
 {
     use leptos_axum::LeptosRoutes;
 
+    if let Err(error) = crate::check_endpoints() {
+        panic!("{}", error);
+    }
+
+    // Resources need to be in place before any server callback can run.
+    if crate::resource::register_resources(resources).is_err() {
+        panic!("resources were already registered, the server can only be started once");
+    }
+
     // Setting get_configuration(None) means we'll be using cargo-leptos's env values
     // For deployment these variables are:
     // <https://github.com/leptos-rs/start-axum#executing-a-server-on-a-remote-machine-without-the-toolchain>
@@ -0,0 +1,64 @@
+/// Serialized callback output together with its HMAC signature. Values computed on the server are
+/// sent to the browser with their signed form, which is what the browser sends back when they're
+/// needed as required state. The server only trusts the bytes it signed itself, so outputs can't
+/// be tampered with. The signature covers the state the value belongs to, so a value can't be
+/// sent to the endpoint of another state (e.g. from `Dash<Revenue>` to `Dash<Cost>`). Note that a
+/// signed value can still be replayed within its state (e.g. an older value of the same field),
+/// which is inherent to the stateless design.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SignedValue {
+    pub bytes: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+#[cfg(feature = "ssr")]
+type HmacSha256 = hmac::Hmac<sha2::Sha256>;
+
+// The key is read from DUST_SIGNING_KEY. A random key is used otherwise, which means signed
+// values don't survive server restarts and can't be shared between multiple server instances.
+#[cfg(feature = "ssr")]
+static SIGNING_KEY: once_cell::sync::Lazy<Vec<u8>> = once_cell::sync::Lazy::new(|| {
+    if let Ok(key) = std::env::var("DUST_SIGNING_KEY") {
+        return key.into_bytes();
+    }
+    leptos::logging::log!("DUST_SIGNING_KEY is not set, using a random signing key");
+    let mut key = vec![0u8; 32];
+    getrandom::getrandom(&mut key).expect("failed to generate a random signing key");
+    key
+});
+
+// The MAC of a value in `context`, which is prefixed with its length so that the context and the
+// bytes can't be split differently.
+#[cfg(feature = "ssr")]
+fn new_mac(context: &str) -> HmacSha256 {
+    use hmac::Mac;
+    let mut mac =
+        HmacSha256::new_from_slice(&SIGNING_KEY).expect("HMAC accepts keys of any size");
+    mac.update(&(context.len() as u64).to_be_bytes());
+    mac.update(context.as_bytes());
+    return mac;
+}
+
+// `context` identifies the state the value belongs to, the executor uses the state's type name.
+#[cfg(feature = "ssr")]
+pub fn sign<V: serde::Serialize>(context: &str, value: &V) -> SignedValue {
+    use hmac::Mac;
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).expect("failed to serialize value");
+    let mut mac = new_mac(context);
+    mac.update(&bytes);
+    SignedValue {
+        bytes,
+        signature: mac.finalize().into_bytes().to_vec(),
+    }
+}
+
+// Returns the value only if it was signed with the same context.
+#[cfg(feature = "ssr")]
+pub fn verify<V: serde::de::DeserializeOwned>(context: &str, signed: &SignedValue) -> Option<V> {
+    use hmac::Mac;
+    let mut mac = new_mac(context);
+    mac.update(&signed.bytes);
+    mac.verify_slice(&signed.signature).ok()?;
+    ciborium::from_reader(signed.bytes.as_slice()).ok()
+}
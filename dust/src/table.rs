@@ -0,0 +1,417 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The value of a table cell.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Cell {
+    #[default]
+    Empty,
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl Cell {
+    // Orders numbers numerically and everything else by its text, empty cells first.
+    pub fn compare(&self, other: &Cell) -> Ordering {
+        match (self, other) {
+            (Cell::Empty, Cell::Empty) => Ordering::Equal,
+            (Cell::Empty, _) => Ordering::Less,
+            (_, Cell::Empty) => Ordering::Greater,
+            (Cell::Number(a), Cell::Number(b)) => a.total_cmp(b),
+            (Cell::Bool(a), Cell::Bool(b)) => a.cmp(b),
+            (a, b) => a.to_string().cmp(&b.to_string()),
+        }
+    }
+
+    // Parses text typed in an editable cell, keeping the kind of value the cell had when possible.
+    pub fn parse_like(&self, text: &str) -> Cell {
+        let text = text.trim();
+        if text.is_empty() {
+            return Cell::Empty;
+        }
+        match self {
+            Cell::Number(_) | Cell::Empty => {
+                if let Ok(number) = text.parse::<f64>() {
+                    return Cell::Number(number);
+                }
+            }
+            Cell::Bool(_) => {
+                if let Ok(value) = text.parse::<bool>() {
+                    return Cell::Bool(value);
+                }
+            }
+            Cell::Text(_) => {}
+        }
+        return Cell::Text(text.to_string());
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cell::Empty => Ok(()),
+            Cell::Text(text) => write!(f, "{}", text),
+            Cell::Number(number) => write!(f, "{}", number),
+            Cell::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Cell {
+        return Cell::Text(value);
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Cell {
+        return Cell::Text(value.to_string());
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(value: f64) -> Cell {
+        return Cell::Number(value);
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(value: i64) -> Cell {
+        return Cell::Number(value as f64);
+    }
+}
+
+impl From<bool> for Cell {
+    fn from(value: bool) -> Cell {
+        return Cell::Bool(value);
+    }
+}
+
+impl<T: Into<Cell>> From<Option<T>> for Cell {
+    fn from(value: Option<T>) -> Cell {
+        return value.map(Into::into).unwrap_or(Cell::Empty);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Column {
+    // Identifies the column in sorts, filters and edits.
+    pub name: String,
+    pub title: String,
+    pub sortable: bool,
+    pub filterable: bool,
+    pub editable: bool,
+}
+
+impl Column {
+    pub fn new(name: impl Into<String>, title: impl Into<String>) -> Column {
+        Column {
+            name: name.into(),
+            title: title.into(),
+            sortable: true,
+            filterable: true,
+            editable: false,
+        }
+    }
+
+    pub fn with_sortable(mut self, sortable: bool) -> Self {
+        self.sortable = sortable;
+        return self;
+    }
+
+    pub fn with_filterable(mut self, filterable: bool) -> Self {
+        self.filterable = filterable;
+        return self;
+    }
+
+    pub fn with_editable(mut self, editable: bool) -> Self {
+        self.editable = editable;
+        return self;
+    }
+}
+
+/// A row of cells, in the order of the columns. The key identifies the row in selections and
+/// edits, so it has to be stable across sorts and pages.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Row {
+    pub key: String,
+    pub cells: Vec<Cell>,
+}
+
+impl Row {
+    pub fn new(key: impl Into<String>, cells: Vec<Cell>) -> Row {
+        Row {
+            key: key.into(),
+            cells,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sort {
+    pub column: String,
+    pub descending: bool,
+}
+
+/// A filter expression on a column: `> 10`, `<= 3.5`, `= open`, `!= closed`, or plain text
+/// matching the cells containing it, ignoring case. An operator without an operand, e.g. while
+/// it's being typed, matches every cell.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Filter {
+    pub column: String,
+    pub expression: String,
+}
+
+impl Filter {
+    pub fn matches(&self, cell: &Cell) -> bool {
+        let expression = self.expression.trim();
+        // Two characters operators first, so `>=` isn't read as `>`.
+        for operator in ["<=", ">=", "!=", "<", ">", "="] {
+            let Some(operand) = expression.strip_prefix(operator) else {
+                continue;
+            };
+            if operand.trim().is_empty() {
+                return true;
+            }
+            let ordering = cell.compare(&cell.parse_like(operand));
+            return match operator {
+                "<=" => ordering != Ordering::Greater,
+                ">=" => ordering != Ordering::Less,
+                "!=" => ordering != Ordering::Equal,
+                "<" => ordering == Ordering::Less,
+                ">" => ordering == Ordering::Greater,
+                _ => ordering == Ordering::Equal,
+            };
+        }
+        return cell
+            .to_string()
+            .to_lowercase()
+            .contains(&expression.to_lowercase());
+    }
+}
+
+/// The new value of a cell edited in a `DataTable`, to be applied by a callback taking it as an
+/// input.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CellEdit {
+    pub row: String,
+    pub column: String,
+    pub value: Cell,
+}
+
+/// The rows of a table shown by `dust::components::DataTable`. Datasets stay on the server and
+/// callbacks set a page field with `TablePage::query`, so only the visible rows are sent:
+///
+/// ```ignore
+/// #[dust_define_callback(Orders, outputs(table))]
+/// fn orders_table(sort: Input<&Option<Sort>>, filters: Input<&Vec<Filter>>, page: Input<usize>) -> TablePage {
+///     return TablePage::query(columns(), load_orders(), sort.value, filters.value, page.value, 20);
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TablePage {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Row>,
+    // Number of rows matching the filters, over all pages.
+    pub total_rows: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+impl TablePage {
+    // Filters and sorts the rows, returning the given page. Pages past the last one are clamped
+    // to it, e.g. after a filter leaves fewer rows.
+    pub fn query(
+        columns: Vec<Column>,
+        rows: impl IntoIterator<Item = Row>,
+        sort: &Option<Sort>,
+        filters: &[Filter],
+        page: usize,
+        page_size: usize,
+    ) -> TablePage {
+        let column_index =
+            |name: &str| columns.iter().position(|column| column.name == name);
+        let filters: Vec<(usize, &Filter)> = filters
+            .iter()
+            .filter(|filter| !filter.expression.trim().is_empty())
+            .filter_map(|filter| Some((column_index(&filter.column)?, filter)))
+            .collect();
+        let mut rows: Vec<Row> = rows
+            .into_iter()
+            .filter(|row| {
+                filters.iter().all(|(index, filter)| {
+                    filter.matches(row.cells.get(*index).unwrap_or(&Cell::Empty))
+                })
+            })
+            .collect();
+
+        if let Some(sort) = sort {
+            if let Some(index) = column_index(&sort.column) {
+                let empty = Cell::Empty;
+                rows.sort_by(|a, b| {
+                    let ordering = a
+                        .cells
+                        .get(index)
+                        .unwrap_or(&empty)
+                        .compare(b.cells.get(index).unwrap_or(&empty));
+                    return if sort.descending { ordering.reverse() } else { ordering };
+                });
+            }
+        }
+
+        let page_size = page_size.max(1);
+        let total_rows = rows.len();
+        let page = page.min(total_rows.saturating_sub(1) / page_size);
+        let rows = rows
+            .into_iter()
+            .skip(page * page_size)
+            .take(page_size)
+            .collect();
+        TablePage {
+            columns,
+            rows,
+            total_rows,
+            page,
+            page_size,
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        return self.total_rows.div_ceil(self.page_size.max(1)).max(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(expression: &str) -> Filter {
+        Filter {
+            column: "value".to_string(),
+            expression: expression.to_string(),
+        }
+    }
+
+    fn columns() -> Vec<Column> {
+        vec![Column::new("name", "Name"), Column::new("qty", "Quantity")]
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row::new("a", vec!["apple".into(), 3.0.into()]),
+            Row::new("b", vec!["banana".into(), Cell::Empty]),
+            Row::new("c", vec!["cherry".into(), 10.0.into()]),
+            Row::new("d", vec!["date".into(), 1.0.into()]),
+            Row::new("e", vec!["elderberry".into(), 7.0.into()]),
+        ]
+    }
+
+    fn keys(page: &TablePage) -> Vec<&str> {
+        page.rows.iter().map(|row| row.key.as_str()).collect()
+    }
+
+    fn sort(column: &str, descending: bool) -> Option<Sort> {
+        Some(Sort {
+            column: column.to_string(),
+            descending,
+        })
+    }
+
+    #[test]
+    fn filter_operators_compare_numbers() {
+        let cell = Cell::Number(10.0);
+        assert!(filter("> 9.5").matches(&cell));
+        assert!(!filter(">10").matches(&cell));
+        assert!(filter(">= 10").matches(&cell));
+        assert!(filter("< 11").matches(&cell));
+        assert!(!filter("<10").matches(&cell));
+        assert!(filter("<= 10").matches(&cell));
+        assert!(filter("= 10").matches(&cell));
+        assert!(filter("!= 9").matches(&cell));
+        assert!(!filter("!= 10").matches(&cell));
+    }
+
+    #[test]
+    fn filter_operators_compare_text() {
+        let cell = Cell::from("open");
+        assert!(filter("= open").matches(&cell));
+        assert!(filter("!= closed").matches(&cell));
+        assert!(filter("> apple").matches(&cell));
+        assert!(!filter("< apple").matches(&cell));
+        // Plain text matches the cells containing it, ignoring case.
+        assert!(filter("PE").matches(&cell));
+        assert!(!filter("closed").matches(&cell));
+        assert!(filter("").matches(&cell));
+    }
+
+    #[test]
+    fn filter_operator_without_operand_matches_every_cell() {
+        for expression in ["<", ">=", "= ", "!="] {
+            assert!(filter(expression).matches(&Cell::Number(1.0)), "{}", expression);
+            assert!(filter(expression).matches(&Cell::Empty), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn filter_on_mixed_cells() {
+        // Operands that aren't numbers are compared with the text of number cells.
+        assert!(filter("= abc").matches(&Cell::from("abc")));
+        assert!(!filter("= abc").matches(&Cell::Number(5.0)));
+        assert!(filter("!= abc").matches(&Cell::Number(5.0)));
+        // Text cells compare as text, even with numeric operands.
+        assert!(!filter("> 9").matches(&Cell::from("10")));
+        // Empty cells are lower than any value.
+        assert!(filter("< 0").matches(&Cell::Empty));
+        assert!(!filter("> 0").matches(&Cell::Empty));
+        assert!(filter("true").matches(&Cell::Bool(true)));
+    }
+
+    #[test]
+    fn query_sorts_empty_cells_first() {
+        let page = TablePage::query(columns(), rows(), &sort("qty", false), &[], 0, 10);
+        assert_eq!(keys(&page), vec!["b", "d", "a", "e", "c"]);
+
+        let page = TablePage::query(columns(), rows(), &sort("qty", true), &[], 0, 10);
+        assert_eq!(keys(&page), vec!["c", "e", "a", "d", "b"]);
+    }
+
+    #[test]
+    fn query_ignores_unknown_columns() {
+        let filters = vec![Filter {
+            column: "price".to_string(),
+            expression: "> 3".to_string(),
+        }];
+        let page = TablePage::query(columns(), rows(), &sort("price", false), &filters, 0, 10);
+        assert_eq!(keys(&page), vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn query_filters_before_paging() {
+        let filters = vec![Filter {
+            column: "qty".to_string(),
+            expression: ">= 3".to_string(),
+        }];
+        let page = TablePage::query(columns(), rows(), &sort("qty", false), &filters, 1, 2);
+        assert_eq!(keys(&page), vec!["c"]);
+        assert_eq!(page.total_rows, 3);
+        assert_eq!(page.page_count(), 2);
+    }
+
+    #[test]
+    fn query_clamps_pages_past_the_end() {
+        let page = TablePage::query(columns(), rows(), &None, &[], 10, 2);
+        assert_eq!(page.page, 2);
+        assert_eq!(keys(&page), vec!["e"]);
+    }
+
+    #[test]
+    fn query_without_rows_has_one_empty_page() {
+        let page = TablePage::query(columns(), Vec::new(), &None, &[], 3, 0);
+        assert_eq!(page.page, 0);
+        assert!(page.rows.is_empty());
+        assert_eq!(page.total_rows, 0);
+        assert_eq!(page.page_count(), 1);
+    }
+}
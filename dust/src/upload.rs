@@ -0,0 +1,167 @@
+use leptos::ServerFnError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Size of the chunks `dust::components::FileUpload` sends files in.
+pub const CHUNK_SIZE: usize = 512 * 1024;
+
+/// Uploads are kept in memory, up to this size each.
+#[cfg(feature = "ssr")]
+pub const MAX_UPLOAD_SIZE: usize = 64 * 1024 * 1024;
+/// Total size of the uploads kept in memory. Uploads count with their whole size from their first
+/// chunk, so that uploads started and never finished can't use up the memory of the server.
+#[cfg(feature = "ssr")]
+pub const MAX_TOTAL_UPLOAD_SIZE: usize = 256 * 1024 * 1024;
+/// Number of uploads that can be in progress at the same time.
+#[cfg(feature = "ssr")]
+pub const MAX_PENDING_UPLOADS: usize = 16;
+// Uploads are dropped once they are this old, or when no chunk was received for a while before
+// they were complete.
+#[cfg(feature = "ssr")]
+const UPLOAD_LIFETIME: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+#[cfg(feature = "ssr")]
+const PENDING_UPLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2 * 60);
+
+/// A file uploaded from the browser by `dust::components::FileUpload`, as a state field:
+///
+/// ```ignore
+/// #[dust_define_callback(Import, outputs(rows))]
+/// fn count_rows(file: Input<&Upload>) -> usize {
+///     return file.value.bytes().map(|bytes| bytes.split(|b| *b == b'\n').count()).unwrap_or(0);
+/// }
+/// ```
+///
+/// The bytes are sent in chunks to a separate endpoint and kept on the server, so updates of the
+/// field only carry the id of the upload along with the name, MIME type and size of the file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Upload {
+    pub id: String,
+    pub name: String,
+    pub mime_type: String,
+    pub size: usize,
+}
+
+impl Upload {
+    // Whether no file was uploaded yet.
+    pub fn is_empty(&self) -> bool {
+        return self.id.is_empty();
+    }
+
+    // The bytes of the file, on the server, once all of them were received. Clients set the
+    // field, so the upload also has to match the name, MIME type and size the file was sent with.
+    pub fn bytes(&self) -> Option<Arc<Vec<u8>>> {
+        let uploads = lock_uploads();
+        let stored = uploads.get(&self.id)?;
+        if !stored.is_complete() || stored.upload != *self {
+            return None;
+        }
+        return Some(stored.bytes.clone());
+    }
+}
+
+struct StoredUpload {
+    upload: Upload,
+    bytes: Arc<Vec<u8>>,
+    #[cfg(feature = "ssr")]
+    started: std::time::Instant,
+    #[cfg(feature = "ssr")]
+    last_chunk: std::time::Instant,
+}
+
+impl StoredUpload {
+    fn is_complete(&self) -> bool {
+        self.bytes.len() == self.upload.size
+    }
+}
+
+static UPLOADS: once_cell::sync::Lazy<Mutex<HashMap<String, StoredUpload>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+// The uploads are only changed by statements that don't panic, so they are still consistent if a
+// panic poisoned the mutex, and later uploads keep working.
+fn lock_uploads() -> MutexGuard<'static, HashMap<String, StoredUpload>> {
+    UPLOADS.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(feature = "ssr")]
+fn purge_expired_uploads(uploads: &mut HashMap<String, StoredUpload>) {
+    uploads.retain(|_, stored| {
+        stored.started.elapsed() < UPLOAD_LIFETIME
+            && (stored.is_complete() || stored.last_chunk.elapsed() < PENDING_UPLOAD_TIMEOUT)
+    });
+}
+
+/// Receives a chunk of a file being uploaded. The first chunk has an empty `id` and starts a new
+/// upload, whose id is returned to send the next chunks, in order.
+#[leptos::server(UploadChunk, "/dust", "Cbor", "upload")]
+pub async fn upload_chunk(
+    id: String,
+    name: String,
+    mime_type: String,
+    size: usize,
+    offset: usize,
+    data: Vec<u8>,
+) -> Result<String, ServerFnError> {
+    if size > MAX_UPLOAD_SIZE {
+        return Err(ServerFnError::new(format!(
+            "{} is too large, uploads can be at most {} bytes",
+            name, MAX_UPLOAD_SIZE
+        )));
+    }
+    let mut uploads = lock_uploads();
+    purge_expired_uploads(&mut uploads);
+    let id = if id.is_empty() {
+        let pending = uploads.values().filter(|stored| !stored.is_complete()).count();
+        if pending >= MAX_PENDING_UPLOADS {
+            return Err(ServerFnError::new("too many uploads are in progress, try again later"));
+        }
+        let total_size: usize = uploads.values().map(|stored| stored.upload.size).sum();
+        if total_size + size > MAX_TOTAL_UPLOAD_SIZE {
+            return Err(ServerFnError::new(format!(
+                "there's no room for {} on the server, try again later",
+                name
+            )));
+        }
+        let mut random = [0u8; 16];
+        getrandom::getrandom(&mut random)
+            .map_err(|error| ServerFnError::new(format!("failed to generate an upload id: {}", error)))?;
+        let id: String = random.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let upload = Upload {
+            id: id.clone(),
+            name,
+            mime_type,
+            size,
+        };
+        uploads.insert(
+            id.clone(),
+            StoredUpload {
+                upload,
+                bytes: Arc::new(Vec::new()),
+                started: std::time::Instant::now(),
+                last_chunk: std::time::Instant::now(),
+            },
+        );
+        id
+    } else {
+        id
+    };
+
+    let Some(stored) = uploads.get_mut(&id) else {
+        return Err(ServerFnError::new(format!("unknown upload {}", id)));
+    };
+    let received = stored.bytes.len();
+    // The upload is kept, so that a chunk sent again after a lost response doesn't fail it.
+    if offset != received || received + data.len() > stored.upload.size {
+        return Err(ServerFnError::new(format!(
+            "unexpected chunk at {} for upload {}",
+            offset, id
+        )));
+    }
+    if !data.is_empty() {
+        // Only incomplete uploads get here, whose bytes aren't shared by `Upload::bytes`.
+        Arc::make_mut(&mut stored.bytes).extend_from_slice(&data);
+        stored.last_chunk = std::time::Instant::now();
+    }
+    return Ok(id);
+}
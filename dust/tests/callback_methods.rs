@@ -0,0 +1,96 @@
+use dust::{dust_define_callbacks, DustState};
+
+// Methods with the same name on different states of a module each get their own callback.
+#[derive(Clone, Debug, Default, DustState)]
+pub struct Cart {
+    pub price: u32,
+    pub total: u32,
+}
+
+#[dust_define_callbacks]
+impl Cart {
+    #[dust_callback(inputs(price), outputs(total))]
+    fn compute(price: u32) -> u32 {
+        price * 2
+    }
+}
+
+#[derive(Clone, Debug, Default, DustState)]
+pub struct Order {
+    pub qty: u32,
+    pub count: u32,
+}
+
+#[dust_define_callbacks]
+impl Order {
+    #[dust_callback(inputs(qty), outputs(count))]
+    fn compute(qty: u32) -> u32 {
+        qty + 1
+    }
+}
+
+pub trait Factor:
+    Clone + std::fmt::Debug + Default + Send + Sync + serde::Serialize + serde::de::DeserializeOwned
+{
+    fn factor() -> u32;
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Triple;
+
+impl Factor for Triple {
+    fn factor() -> u32 {
+        3
+    }
+}
+
+// Generic states list their methods, the same way as their functions.
+#[derive(Clone, Debug, Default, DustState)]
+#[dust(instance(Scaled<Triple>))]
+#[dust_register_callback(Self::compute)]
+pub struct Scaled<F: Factor> {
+    pub raw: u32,
+    pub scaled: u32,
+    pub factor: F,
+}
+
+#[dust_define_callbacks]
+impl<F: Factor> Scaled<F> {
+    #[dust_callback(inputs(raw), outputs(scaled))]
+    fn compute(raw: u32) -> u32 {
+        raw * F::factor()
+    }
+}
+
+#[test]
+fn methods_with_the_same_name_on_different_states() {
+    let mut cart = Cart {
+        price: 5,
+        ..Default::default()
+    };
+    let callbacks = Cart::get_registered_callbacks();
+    assert_eq!(callbacks.len(), 1);
+    let updates = (callbacks[0].cb)(&mut cart).unwrap();
+    <Cart as dust::ApplyUpdates<_>>::apply_updates(&mut cart, &updates);
+    assert_eq!(cart.total, 10);
+
+    let mut order = Order {
+        qty: 5,
+        ..Default::default()
+    };
+    let callbacks = Order::get_registered_callbacks();
+    assert_eq!(callbacks.len(), 1);
+    let updates = (callbacks[0].cb)(&mut order).unwrap();
+    <Order as dust::ApplyUpdates<_>>::apply_updates(&mut order, &updates);
+    assert_eq!(order.count, 6);
+
+    let mut scaled = Scaled::<Triple> {
+        raw: 5,
+        ..Default::default()
+    };
+    let callbacks = Scaled::<Triple>::get_registered_callbacks();
+    assert_eq!(callbacks.len(), 1);
+    let updates = (callbacks[0].cb)(&mut scaled).unwrap();
+    <Scaled<Triple> as dust::ApplyUpdates<_>>::apply_updates(&mut scaled, &updates);
+    assert_eq!(scaled.scaled, 15);
+}
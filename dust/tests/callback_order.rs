@@ -0,0 +1,28 @@
+use dust::{dust_define_callback, DustState, InOut, Input, Output};
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust_register_callback(add_step)]
+#[dust_register_callback(reset_count)]
+pub struct Counter {
+    pub step: u32,
+    pub start: u32,
+    pub count: u32,
+}
+
+#[dust_define_callback(Counter)]
+fn add_step(step: Input<u32>, count: &mut InOut<u32>) {
+    **count += step.value;
+}
+
+#[dust_define_callback(Counter)]
+fn reset_count(start: Input<u32>, count: &mut Output<u32>) {
+    count.set(start.value);
+}
+
+#[test]
+fn field_updated_in_place_and_computed_is_reported() {
+    let error = dust::check_endpoints().unwrap_err();
+    assert!(error.contains("Counter"), "{}", error);
+    assert!(error.contains("add_step") && error.contains("reset_count"), "{}", error);
+    assert!(<Counter as dust::StateInstance>::executor().is_err());
+}
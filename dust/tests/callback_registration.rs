@@ -0,0 +1,42 @@
+use dust::{dust_define_callback, DustState, Input, Output};
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust_register_callback(update_total)]
+pub struct Invoice {
+    pub price: u32,
+    pub total: u32,
+    pub taxed_total: u32,
+}
+
+#[dust_define_callback(Invoice)]
+fn update_total(price: Input<u32>, total: &mut Output<u32>) {
+    total.set(price.value * 2);
+}
+
+mod taxes {
+    use super::Invoice;
+    use dust::{dust_define_callback, Input, Output};
+
+    // Same name as the listed callback of the state, but a different callback.
+    #[dust_define_callback(Invoice)]
+    fn update_total(price: Input<u32>, taxed_total: &mut Output<u32>) {
+        taxed_total.set(price.value * 3);
+    }
+}
+
+#[test]
+fn callbacks_with_the_same_name_in_different_modules_both_run() {
+    let callbacks = Invoice::get_registered_callbacks();
+    assert_eq!(callbacks.len(), 2);
+
+    let mut state = Invoice {
+        price: 5,
+        ..Default::default()
+    };
+    for callback in callbacks.iter() {
+        let updates = (callback.cb)(&mut state).unwrap();
+        <Invoice as dust::ApplyUpdates<_>>::apply_updates(&mut state, &updates);
+    }
+    assert_eq!(state.total, 10);
+    assert_eq!(state.taxed_total, 15);
+}
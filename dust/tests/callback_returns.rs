@@ -0,0 +1,81 @@
+#![cfg(feature = "ssr")]
+
+use dust::{dust_define_callback, DustState, Input, StateInstance, StateTypes, UpdateError};
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust_register_callback(discount)]
+#[dust_register_callback(label)]
+#[dust_register_callback(label_length)]
+pub struct Prices {
+    pub price: i64,
+    pub discounted: i64,
+    pub label: String,
+    pub label_length: usize,
+}
+
+type Value = <Prices as StateTypes>::Value;
+
+// Prices above 100 get a discount, the others keep the previous discounted price.
+#[dust_define_callback(Prices, outputs(discounted))]
+fn discount(price: Input<i64>) -> Option<i64> {
+    (price.value > 100).then(|| price.value - 10)
+}
+
+#[dust_define_callback(Prices, outputs(label))]
+fn label(price: Input<i64>) -> Result<String, String> {
+    if price.value > 1000 {
+        return Err("price too high".to_string());
+    }
+    Ok(format!("${}", price.value))
+}
+
+#[dust_define_callback(Prices, outputs(label_length))]
+fn label_length(label: Input<String>) -> usize {
+    label.value.len()
+}
+
+fn process(price: i64) -> dust::UpdateResponse<Value> {
+    let request = dust::http::Request::new(()).into_parts().0;
+    Prices::executor()
+        .unwrap()
+        .process_updates(&request, vec![Value::Price(price)], vec![], vec![])
+        .unwrap()
+}
+
+// Sorted, since independent callbacks can run in any order.
+fn updates(response: &dust::UpdateResponse<Value>) -> Vec<String> {
+    let mut updates: Vec<String> =
+        response.updates.iter().map(|(value, _)| format!("{:?}", value)).collect();
+    updates.sort();
+    updates
+}
+
+#[test]
+fn returned_some_sets_the_output() {
+    let response = process(150);
+    assert_eq!(
+        updates(&response),
+        vec!["Discounted(140)", "Label(\"$150\")", "LabelLength(4)"]
+    );
+    assert!(response.errors.is_empty());
+}
+
+#[test]
+fn returned_none_leaves_the_output_unchanged() {
+    let response = process(50);
+    assert_eq!(updates(&response), vec!["Label(\"$50\")", "LabelLength(3)"]);
+    assert!(response.errors.is_empty());
+}
+
+#[test]
+fn returned_err_skips_the_callback_dependants() {
+    let response = process(2000);
+    // The discount doesn't depend on the label and still runs.
+    assert_eq!(updates(&response), vec!["Discounted(1990)"]);
+    assert_eq!(response.errors.len(), 1);
+    assert!(matches!(
+        &response.errors[0],
+        UpdateError::CallbackFailed { callback, message }
+            if callback.contains("label") && message == "price too high"
+    ));
+}
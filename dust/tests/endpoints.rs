@@ -0,0 +1,20 @@
+use dust::DustState;
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust(endpoint = "checkout")]
+pub struct Cart {
+    pub total: u32,
+}
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust(endpoint = "checkout")]
+pub struct Payment {
+    pub amount: u32,
+}
+
+#[test]
+fn states_sharing_an_endpoint_are_reported() {
+    let error = dust::check_endpoints().unwrap_err();
+    assert!(error.contains("/server_callback/checkout"), "{}", error);
+    assert!(error.contains("Cart") && error.contains("Payment"), "{}", error);
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "ssr")]
+
+use dust::{dust_define_callback, DustState, Input, Output, StateInstance, StateTypes};
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust(embedded)]
+pub struct Address {
+    pub country: String,
+    pub city: String,
+}
+
+// Two fields expanding the same state.
+#[derive(Clone, Debug, Default, DustState)]
+#[dust_register_callback(billing_country_label)]
+#[dust_register_callback(billing_city_label)]
+#[dust_register_callback(shipping_country_label)]
+pub struct Checkout {
+    #[dust(expand)]
+    pub billing: Address,
+    #[dust(expand)]
+    pub shipping: Address,
+    pub billing_country: String,
+    pub billing_city: String,
+    pub shipping_country: String,
+}
+
+#[dust_define_callback(Checkout)]
+fn billing_country_label(
+    #[field(billing.country)] country: Input<String>,
+    billing_country: &mut Output<String>,
+) {
+    billing_country.set(country.value.to_uppercase());
+}
+
+#[dust_define_callback(Checkout)]
+fn billing_city_label(
+    #[field(billing.city)] city: Input<String>,
+    billing_city: &mut Output<String>,
+) {
+    billing_city.set(city.value.to_uppercase());
+}
+
+#[dust_define_callback(Checkout)]
+fn shipping_country_label(
+    #[field(shipping.country)] country: Input<String>,
+    shipping_country: &mut Output<String>,
+) {
+    shipping_country.set(country.value.to_uppercase());
+}
+
+type Value = <Checkout as StateTypes>::Value;
+type AddressValue = <Address as StateTypes>::Value;
+
+#[test]
+fn updating_a_field_path_only_runs_the_callbacks_bound_to_it() {
+    let request = dust::http::Request::new(()).into_parts().0;
+    let response = Checkout::executor()
+        .unwrap()
+        .process_updates(
+            &request,
+            vec![Value::Billing(AddressValue::Country("fr".to_string()))],
+            vec![],
+            vec![],
+        )
+        .unwrap();
+    let updates: Vec<String> =
+        response.updates.iter().map(|(value, _)| format!("{:?}", value)).collect();
+    assert_eq!(updates, vec!["BillingCountry(\"FR\")"]);
+}
+
+#[test]
+fn embedded_states_have_no_endpoint() {
+    assert!(Address::executor().is_err());
+    dust::check_endpoints().unwrap();
+}
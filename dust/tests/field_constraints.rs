@@ -0,0 +1,83 @@
+#![cfg(feature = "ssr")]
+
+use dust::leptos::SignalGetUntracked;
+use dust::{dust_define_callback, DustState, Input, Output, StateInstance, StateTypes, UpdateError};
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust_register_callback(describe)]
+pub struct Order {
+    #[dust(range(1..=10))]
+    pub qty: u32,
+    #[dust(max_len = 3)]
+    pub code: String,
+    pub description: String,
+    #[dust(client_only, step = 100)]
+    pub level: u8,
+    #[dust(client_only)]
+    pub offset: i8,
+}
+
+#[dust_define_callback(Order)]
+fn describe(qty: Input<u32>, code: Input<String>, description: &mut Output<String>) {
+    description.set(format!("{} x {}", qty.value, code.value));
+}
+
+type Value = <Order as StateTypes>::Value;
+
+fn validate(input_updates: Vec<Value>, required_state: Vec<Value>) -> Result<(), UpdateError> {
+    Order::executor()
+        .unwrap()
+        .validate_updates(&input_updates, &required_state, &[])
+}
+
+fn invalid_field(result: Result<(), UpdateError>) -> Option<String> {
+    match result {
+        Err(UpdateError::InvalidValue { field, .. }) => Some(field),
+        _ => None,
+    }
+}
+
+#[test]
+fn values_out_of_range_are_rejected() {
+    assert!(validate(vec![Value::Qty(10)], vec![Value::Code("abc".to_string())]).is_ok());
+    let result = validate(vec![Value::Qty(11)], vec![Value::Code("abc".to_string())]);
+    assert_eq!(invalid_field(result), Some("qty".to_string()));
+    let result = validate(vec![Value::Qty(0)], vec![Value::Code("abc".to_string())]);
+    assert_eq!(invalid_field(result), Some("qty".to_string()));
+}
+
+#[test]
+fn values_too_long_are_rejected_in_the_required_state() {
+    let result = validate(vec![Value::Qty(2)], vec![Value::Code("abcd".to_string())]);
+    assert_eq!(invalid_field(result), Some("code".to_string()));
+}
+
+#[test]
+fn max_len_counts_characters() {
+    // Six bytes, three characters.
+    assert!(validate(vec![Value::Code("äöü".to_string())], vec![Value::Qty(2)]).is_ok());
+    let result = validate(vec![Value::Code("äöüß".to_string())], vec![Value::Qty(2)]);
+    assert_eq!(invalid_field(result), Some("code".to_string()));
+}
+
+#[test]
+fn increment_and_decrement_saturate() {
+    let runtime = dust::leptos::create_runtime();
+    Order::provide_and_initiaze_context();
+    let ctx = Order::expect_context();
+
+    ctx.increment_level();
+    ctx.increment_level();
+    ctx.increment_level();
+    assert_eq!(ctx.level.get_untracked(), u8::MAX);
+    ctx.decrement_level();
+    assert_eq!(ctx.level.get_untracked(), 155);
+
+    ctx.set_offset(-127);
+    ctx.decrement_offset();
+    ctx.decrement_offset();
+    assert_eq!(ctx.offset.get_untracked(), i8::MIN);
+    ctx.increment_offset();
+    assert_eq!(ctx.offset.get_untracked(), -127);
+    runtime.dispose();
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "ssr")]
+
+use dust::{dust_define_callback, require_role, DustState, Input, Output, StateInstance, StateTypes};
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust(endpoint = "refunds")]
+pub struct Refund {
+    pub order: u64,
+    pub refunded: bool,
+}
+
+#[dust_define_callback(Refund, guard = require_role("admin"))]
+fn refund(order: Input<u64>, refunded: &mut Output<bool>) {
+    refunded.set(order.value > 0);
+}
+
+#[test]
+fn server_callback_fails_closed_without_the_request() {
+    let runtime = dust::tokio::runtime::Builder::new_current_thread().build().unwrap();
+    // Outside of a request there are no Parts to check the guard against, so nothing runs.
+    let result = runtime.block_on(Refund::server_callback(
+        vec![<Refund as StateTypes>::Value::Order(7)],
+        vec![],
+        vec![],
+    ));
+    let error = result.unwrap_err();
+    assert!(error.to_string().contains("request isn't available"), "{}", error);
+}
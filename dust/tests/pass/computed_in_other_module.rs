@@ -0,0 +1,34 @@
+// The computed function lives in a different module than the state using it.
+mod shop {
+    use dust::DustState;
+
+    #[derive(Clone, Debug, Default, DustState)]
+    pub struct Order {
+        pub price: u32,
+        pub qty: u32,
+        #[dust(computed = crate::pricing::total)]
+        pub total: u32,
+    }
+}
+
+mod pricing {
+    use dust::dust_computed;
+
+    #[dust_computed]
+    pub fn total(price: u32, qty: &u32) -> u32 {
+        price * qty
+    }
+}
+
+fn main() {
+    let mut order = shop::Order {
+        price: 3,
+        qty: 4,
+        ..Default::default()
+    };
+    let callbacks = shop::Order::get_registered_callbacks();
+    assert_eq!(callbacks.len(), 1);
+    let updates = (callbacks[0].cb)(&mut order).unwrap();
+    <shop::Order as dust::ApplyUpdates<_>>::apply_updates(&mut order, &updates);
+    assert_eq!(order.total, 12);
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "ssr")]
+
+use dust::resource::register_resources;
+use dust::{
+    dust_define_callback, DustState, Input, Output, Resource, Resources, StateInstance, StateTypes,
+    UpdateError,
+};
+
+pub struct TaxRate(u32);
+
+// Never registered.
+pub struct Discount(u32);
+
+#[derive(Clone, Debug, Default, DustState)]
+#[dust_register_callback(compute_taxed)]
+#[dust_register_callback(compute_discounted)]
+pub struct Invoice {
+    pub price: u32,
+    pub taxed: u32,
+    pub discounted: u32,
+}
+
+#[dust_define_callback(Invoice)]
+fn compute_taxed(price: Input<u32>, rate: Resource<TaxRate>, taxed: &mut Output<u32>) {
+    taxed.set(price.value * (100 + rate.0) / 100);
+}
+
+#[dust_define_callback(Invoice)]
+fn compute_discounted(price: Input<u32>, discount: Resource<Discount>, discounted: &mut Output<u32>) {
+    discounted.set(price.value - discount.0);
+}
+
+type Value = <Invoice as StateTypes>::Value;
+
+#[test]
+fn callbacks_receive_their_resources() {
+    // Tests share the registered resources of the process, each registers the same ones.
+    let _ = register_resources(Resources::new().with(TaxRate(20)));
+    let request = dust::http::Request::new(()).into_parts().0;
+    let response = Invoice::executor()
+        .unwrap()
+        .process_updates(&request, vec![Value::Price(50)], vec![], vec![])
+        .unwrap();
+
+    let updates: Vec<String> =
+        response.updates.iter().map(|(value, _)| format!("{:?}", value)).collect();
+    assert_eq!(updates, vec!["Taxed(60)"]);
+    // The callback needing a missing resource fails instead of panicking the server.
+    assert_eq!(response.errors.len(), 1);
+    assert!(matches!(
+        &response.errors[0],
+        UpdateError::CallbackFailed { callback, message }
+            if callback.contains("compute_discounted") && message.contains("Discount")
+    ));
+}
+
+#[test]
+fn resources_are_registered_once() {
+    let _ = register_resources(Resources::new().with(TaxRate(20)));
+    assert!(register_resources(Resources::new().with(Discount(5))).is_err());
+    assert!(Resource::<Discount>::get().is_none());
+}
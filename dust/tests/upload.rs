@@ -0,0 +1,52 @@
+#![cfg(feature = "ssr")]
+
+use dust::leptos::ServerFnError;
+use dust::upload::{
+    upload_chunk, Upload, MAX_PENDING_UPLOADS, MAX_TOTAL_UPLOAD_SIZE, MAX_UPLOAD_SIZE,
+};
+
+fn send_chunk(id: &str, size: usize, offset: usize, data: &[u8]) -> Result<String, ServerFnError> {
+    let runtime = dust::tokio::runtime::Builder::new_current_thread().build().unwrap();
+    runtime.block_on(upload_chunk(
+        id.to_string(),
+        "data.csv".to_string(),
+        "text/csv".to_string(),
+        size,
+        offset,
+        data.to_vec(),
+    ))
+}
+
+// A single test since the uploads are shared by the whole process.
+#[test]
+fn uploads_survive_retried_chunks_and_are_limited_in_number_and_total_size() {
+    let id = send_chunk("", 4, 0, b"ab").unwrap();
+    // A chunk sent again is rejected without failing the upload.
+    assert!(send_chunk(&id, 4, 0, b"ab").is_err());
+    assert_eq!(send_chunk(&id, 4, 2, b"cd").unwrap(), id);
+    let upload = Upload {
+        id,
+        name: "data.csv".to_string(),
+        mime_type: "text/csv".to_string(),
+        size: 4,
+    };
+    assert_eq!(upload.bytes().unwrap().as_slice(), b"abcd");
+
+    let pending: Vec<String> = (0..MAX_PENDING_UPLOADS)
+        .map(|_| send_chunk("", 1, 0, &[]).unwrap())
+        .collect();
+    assert!(send_chunk("", 1, 0, &[]).is_err());
+
+    // Finished uploads don't count as in progress.
+    for id in pending.iter() {
+        send_chunk(id, 1, 0, b"x").unwrap();
+    }
+    send_chunk("", 1, 0, &[]).unwrap();
+
+    // Unfinished uploads count with their whole size.
+    let mut started = 0;
+    while send_chunk("", MAX_UPLOAD_SIZE, 0, &[]).is_ok() {
+        started += 1;
+    }
+    assert_eq!(started, MAX_TOTAL_UPLOAD_SIZE / MAX_UPLOAD_SIZE - 1);
+}
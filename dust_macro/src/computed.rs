@@ -0,0 +1,85 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+
+// `#[dust_computed]` marks a function that computes a field from other fields of the state, used
+// with `#[dust(computed = compute_total)]` on that field. Its arguments are named after the fields
+// it reads and take their values (`price: u32`) or references to them (`price: &u32`).
+//
+// The state isn't known here, so along with the function this defines a macro that the derive
+// invokes with the state and the computed field, which defines the callback.
+pub fn dust_computed(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(
+            proc_macro2::TokenStream::from(args).span(),
+            "`#[dust_computed]` doesn't take arguments, the field is set with \
+            `#[dust(computed = ...)]`",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let function = parse_macro_input!(input as syn::ItemFn);
+    return expand_computed(function)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into();
+}
+
+// Name of the macro defined along with a computed function.
+pub fn computed_macro_ident(function: &syn::Ident) -> syn::Ident {
+    syn::Ident::new(&format!("__dust_computed_{}", function), function.span())
+}
+
+fn expand_computed(function: syn::ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &function.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "computed functions can't be generic",
+        ));
+    }
+    let output = match sig.output {
+        syn::ReturnType::Type(_, ref ty) => ty,
+        syn::ReturnType::Default => {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "computed functions need to return the value of the field",
+            ));
+        }
+    };
+
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    for arg in sig.inputs.iter() {
+        let syn::FnArg::Typed(pat_type) = arg else {
+            return Err(syn::Error::new_spanned(arg, "computed functions can't take `self`"));
+        };
+        let syn::Pat::Ident(ref pat_ident) = *pat_type.pat else {
+            return Err(syn::Error::new_spanned(
+                &pat_type.pat,
+                "arguments of computed functions must be named after the fields they read",
+            ));
+        };
+        names.push(&pat_ident.ident);
+        types.push(&pat_type.ty);
+    }
+
+    let macro_ident = computed_macro_ident(&sig.ident);
+    Ok(quote! {
+        #function
+
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #macro_ident {
+            ($name:ident, $function:path, $state:ident, $field:ident) => {
+                #[dust::dust_define_callback($state, outputs($field))]
+                fn $name(#(#names: dust::Input<#types>,)*) -> #output {
+                    return $function(#(#names.value,)*);
+                }
+            };
+        }
+
+        #[allow(unused_imports)]
+        pub(crate) use #macro_ident;
+    })
+}
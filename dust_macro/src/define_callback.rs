@@ -1,103 +1,527 @@
+use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::parse_macro_input;
-use crate::enum_utils::field_to_enum;
+use syn::spanned::Spanned;
 
 enum CallbackArgType {
     Input,
     Output,
+    InOut,
+    Resource,
 }
 
 struct CallbackArg {
     name_ident: syn::Ident,
     arg_type: CallbackArgType,
+    // Path of the state field the argument is bound to. It's the argument name unless set with
+    // `#[field(filters.country)]`.
+    field_path: Vec<syn::Ident>,
+    // `Input<&T>` arguments borrow the field instead of cloning it.
+    borrowed: bool,
+    // Arguments of callback methods take the field value (or a reference to it) instead of an
+    // `Input<T>`.
+    unwrapped: bool,
 }
 
-fn get_arg_name_ident(arg: &syn::FnArg) -> syn::Ident {
-    if let syn::FnArg::Typed(pat_type) = arg {
-        if let syn::Pat::Ident(ref pat_ident) = *pat_type.pat {
-            return pat_ident.ident.clone();
+fn is_borrowed_input(arg: &syn::FnArg) -> bool {
+    let syn::FnArg::Typed(pat_type) = arg else {
+        return false;
+    };
+    let syn::Type::Path(ref path) = *pat_type.ty else {
+        return false;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return false;
+    };
+    let syn::PathArguments::AngleBracketed(ref arguments) = segment.arguments else {
+        return false;
+    };
+    matches!(arguments.args.first(), Some(syn::GenericArgument::Type(syn::Type::Reference(_))))
+}
+
+// Parses a field path like `total` or `filters.country`.
+fn parse_field_path(input: syn::parse::ParseStream) -> syn::Result<Vec<syn::Ident>> {
+    let path = syn::punctuated::Punctuated::<syn::Ident, syn::Token![.]>::parse_separated_nonempty(
+        input,
+    )?;
+    Ok(path.into_iter().collect())
+}
+
+// Returns the type wrapped by `Option<T>` or `Result<T, E>`. They're recognized by the name of the
+// last path segment, so `io::Result<T>` is a Result but an alias with another name isn't, it's
+// taken as the value of the output.
+fn strip_return_wrapper<'a>(ty: &'a syn::Type, wrapper: &str) -> Option<&'a syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(ref arguments) = segment.arguments else {
+        return None;
+    };
+    match arguments.args.first() {
+        Some(syn::GenericArgument::Type(inner)) => Some(inner),
+        _ => None,
+    }
+}
+
+// Takes the `#[field(...)]` attribute out of the argument, so it's not left on the function.
+fn take_field_path(arg: &mut syn::FnArg) -> syn::Result<Option<Vec<syn::Ident>>> {
+    let attrs = match arg {
+        syn::FnArg::Typed(pat_type) => &mut pat_type.attrs,
+        syn::FnArg::Receiver(receiver) => &mut receiver.attrs,
+    };
+    let mut field_path = None;
+    for attr in attrs.iter() {
+        if attr.path().is_ident("field") {
+            if field_path.is_some() {
+                return Err(syn::Error::new_spanned(attr, "duplicate `#[field(...)]` attribute"));
+            }
+            field_path = Some(attr.parse_args_with(parse_field_path)?);
+        }
+    }
+    attrs.retain(|attr| !attr.path().is_ident("field"));
+    Ok(field_path)
+}
+
+// FieldAccess of the state reached through `prefix`, e.g. the one of `FilterPanel` for
+// `filters`.
+fn field_access(state_struct: &syn::Type, prefix: &[syn::Ident]) -> proc_macro2::TokenStream {
+    let get_field = match prefix.split_first() {
+        None => quote! { state },
+        Some((first, [])) => quote! {
+            <<#state_struct as dust::StateTypes>::FieldAccess>::#first(state)
+        },
+        Some((first, rest)) => quote! {
+            &<<#state_struct as dust::StateTypes>::FieldAccess>::#first(state)#(.#rest)*
+        },
+    };
+    quote! {
+        dust::field_access_of(|state: &#state_struct| #get_field)
+    }
+}
+
+// Identifiers of the field at `prefix` + `path`, wrapped into the variants of each enclosing state.
+// A whole nested or expanded field stands for all of its sub-fields.
+fn field_path_identifiers(
+    state_struct: &syn::Type,
+    prefix: &[syn::Ident],
+    path: &[syn::Ident],
+) -> proc_macro2::TokenStream {
+    let access = field_access(state_struct, prefix);
+    let field = &path[0];
+    if path.len() == 1 {
+        let identifiers_fn = syn::Ident::new(&format!("{}_identifiers", field), field.span());
+        return quote! { #access.#identifiers_fn() };
+    }
+    let identifier_fn = syn::Ident::new(&format!("{}_identifier", field), field.span());
+    let prefix = [prefix, &path[..1]].concat();
+    let inner = field_path_identifiers(state_struct, &prefix, &path[1..]);
+    quote! {
+        #inner.into_iter().map(|identifier| #access.#identifier_fn(identifier)).collect::<Vec<_>>()
+    }
+}
+
+// Same as field_path_identifiers, for the values of the field.
+fn field_path_values(
+    state_struct: &syn::Type,
+    prefix: &[syn::Ident],
+    path: &[syn::Ident],
+    value: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let access = field_access(state_struct, prefix);
+    let field = &path[0];
+    if path.len() == 1 {
+        let values_fn = syn::Ident::new(&format!("{}_values", field), field.span());
+        return quote! { #access.#values_fn(#value) };
+    }
+    let value_fn = syn::Ident::new(&format!("{}_value", field), field.span());
+    let prefix = [prefix, &path[..1]].concat();
+    let inner = field_path_values(state_struct, &prefix, &path[1..], value);
+    quote! {
+        #inner.into_iter().map(|value| #access.#value_fn(value)).collect::<Vec<_>>()
+    }
+}
+
+// Borrows the current value of the field bound to the argument.
+fn field_path_borrow(state_struct: &syn::Type, path: &[syn::Ident]) -> proc_macro2::TokenStream {
+    let first = &path[0];
+    let rest = &path[1..];
+    if rest.is_empty() {
+        return quote! {
+            <<#state_struct as dust::StateTypes>::FieldAccess>::#first(app)
+        };
+    }
+    quote! {
+        &<<#state_struct as dust::StateTypes>::FieldAccess>::#first(app)#(.#rest)*
+    }
+}
+
+// Reads a copy of the current value of the field bound to the argument.
+fn field_path_read(state_struct: &syn::Type, path: &[syn::Ident]) -> proc_macro2::TokenStream {
+    let first = &path[0];
+    let rest = &path[1..];
+    quote! {
+        <<#state_struct as dust::StateTypes>::FieldAccess>::#first(app)#(.#rest)*.clone()
+    }
+}
+
+fn get_arg_name_ident(arg: &syn::FnArg) -> syn::Result<syn::Ident> {
+    match arg {
+        syn::FnArg::Typed(pat_type) => {
+            if let syn::Pat::Ident(ref pat_ident) = *pat_type.pat {
+                return Ok(pat_ident.ident.clone());
+            }
+            Err(syn::Error::new_spanned(
+                &pat_type.pat,
+                "callback arguments must be plain identifiers matching state fields, or be bound to a \
+                field with `#[field(...)]`",
+            ))
         }
+        syn::FnArg::Receiver(receiver) => Err(syn::Error::new_spanned(
+            receiver,
+            "callbacks can't take `self`",
+        )),
     }
-    panic!("couldn't extract arg name for {:?}", arg);
 }
 
-fn get_arg_type(arg: &syn::FnArg) -> CallbackArgType {
+fn get_arg_type(arg: &syn::FnArg) -> syn::Result<CallbackArgType> {
     if let syn::FnArg::Typed(pat_type) = arg {
         if let syn::Type::Path(ref path) = *pat_type.ty {
-            if path.path.segments.len() > 0 && path.path.segments[0].ident == "Input" {
-                return CallbackArgType::Input;
+            if path.path.segments.last().is_some_and(|segment| segment.ident == "Input") {
+                return Ok(CallbackArgType::Input);
+            }
+            if path.path.segments.last().is_some_and(|segment| segment.ident == "Resource") {
+                return Ok(CallbackArgType::Resource);
             }
         }
 
         if let syn::Type::Reference(ref type_reference) = *pat_type.ty {
             if let syn::Type::Path(ref path) = *type_reference.elem {
-                if path.path.segments.len() > 0 && path.path.segments[0].ident == "Output" {
-                    return CallbackArgType::Output;
+                if path.path.segments.last().is_some_and(|segment| segment.ident == "Output") {
+                    return Ok(CallbackArgType::Output);
+                }
+                if path.path.segments.last().is_some_and(|segment| segment.ident == "InOut") {
+                    return Ok(CallbackArgType::InOut);
                 }
             }
         }
+
+        return Err(syn::Error::new_spanned(
+            &pat_type.ty,
+            "unexpected callback argument type, expected `Input<T>`, `&mut Output<T>`, \
+            `&mut InOut<T>` or `Resource<T>`",
+        ));
     }
-    panic!(
-        "couldn't extract arg outer type. Expected 'Input<T>' or '&mut Output<T>', \
-        found {:#?}",
-        arg
-    );
+    Err(syn::Error::new_spanned(arg, "unexpected callback argument"))
 }
 
+// Options of a callback: `guard = <expr>`, `outputs(field, ...)`, the fields set from the returned
+// value(s), and for callback methods `inputs(field, ...)`, the fields passed as their arguments.
+#[derive(Default)]
+struct CallbackOptions {
+    guard: Option<syn::Expr>,
+    inputs: Vec<Vec<syn::Ident>>,
+    outputs: Vec<Vec<syn::Ident>>,
+}
+
+impl CallbackOptions {
+    fn parse(input: syn::parse::ParseStream, is_method: bool) -> syn::Result<CallbackOptions> {
+        let mut options = CallbackOptions::default();
+        while !input.is_empty() {
+            let key = input.parse::<syn::Ident>()?;
+            if key == "inputs" || key == "outputs" {
+                if key == "inputs" && !is_method {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "`inputs(...)` is only used by `#[dust_callback]` methods, callback \
+                        functions take `Input<T>` arguments",
+                    ));
+                }
+                let content;
+                syn::parenthesized!(content in input);
+                let paths = syn::punctuated::Punctuated::<Vec<syn::Ident>, syn::Token![,]>
+                    ::parse_terminated_with(&content, parse_field_path)?;
+                if key == "inputs" {
+                    options.inputs.extend(paths);
+                } else {
+                    options.outputs.extend(paths);
+                }
+            } else if key == "guard" {
+                input.parse::<syn::Token![=]>()?;
+                options.guard = Some(input.parse::<syn::Expr>()?);
+            } else {
+                return Err(syn::Error::new(key.span(), format!("unknown argument `{}`", key)));
+            }
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+        Ok(options)
+    }
+}
+
+// Accepts `#[dust_define_callback(State)]` optionally followed by the callback options.
+// The state can be any type path, e.g. `DashboardState<T>` for callbacks generic over the state.
+struct DefineCallbackArgs {
+    state_struct: syn::Type,
+    options: CallbackOptions,
+}
+
+impl syn::parse::Parse for DefineCallbackArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let state_struct = input.parse::<syn::Type>()?;
+        if !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+        }
+        let options = CallbackOptions::parse(input, false)?;
+        Ok(DefineCallbackArgs {
+            state_struct,
+            options,
+        })
+    }
+}
 
 pub fn dust_define_callback(args: TokenStream, input: TokenStream) -> TokenStream {
-    let state_struct = parse_macro_input!(args as syn::Ident);
-    let function = parse_macro_input!(input as syn::Item);
-    // let function = parse_macro_input!(input as syn::Item);
-    // eprintln!("{:#?}", function);
+    let args = parse_macro_input!(args as DefineCallbackArgs);
+    let function = parse_macro_input!(input as syn::ItemFn);
+    return expand_define_callback(args, function)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into();
+}
 
-    let function_name = if let syn::Item::Fn(syn::ItemFn { ref sig, .. }) = function {
-        sig.ident.clone()
-    } else {
-        panic!("missing fuction name")
-    };
+fn expand_define_callback(
+    args: DefineCallbackArgs,
+    mut function: syn::ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let DefineCallbackArgs {
+        state_struct,
+        options,
+    } = args;
+    let function_name = function.sig.ident.clone();
+    let mut field_paths = Vec::new();
+    for arg in function.sig.inputs.iter_mut() {
+        field_paths.push(take_field_path(arg)?);
+    }
+    let (_, ty_generics, _) = function.sig.generics.split_for_impl();
+    let turbofish = ty_generics.as_turbofish();
 
-    let get_callback_args = |item: &syn::Item| -> Vec<CallbackArg> {
+    let get_callback_args = |function: &syn::ItemFn| -> syn::Result<Vec<CallbackArg>> {
         let mut result = Vec::new();
-        if let syn::Item::Fn(syn::ItemFn {
-            sig: syn::Signature { inputs, .. },
-            ..
-        }) = item
+        for (index, (arg, field_path)) in
+            function.sig.inputs.iter().zip(field_paths.iter()).enumerate()
         {
-            for arg in inputs {
-                let arg_name = get_arg_name_ident(arg);
-                let arg_type = get_arg_type(arg);
+            let arg_name = match (get_arg_name_ident(arg), field_path) {
+                (Ok(arg_name), _) => arg_name,
+                // Arguments bound with #[field(...)] can use any pattern, e.g. `Input { value }`.
+                (Err(_), Some(_)) if matches!(arg, syn::FnArg::Typed(_)) => {
+                    syn::Ident::new(&format!("arg_{}", index), arg.span())
+                }
+                (Err(error), _) => return Err(error),
+            };
+            let arg_type = get_arg_type(arg)?;
+            if let (CallbackArgType::Resource, Some(field_path)) = (&arg_type, field_path) {
+                return Err(syn::Error::new(
+                    field_path[0].span(),
+                    "`Resource<T>` arguments aren't state fields and can't use `#[field(...)]`",
+                ));
+            }
+            let field_path = field_path.clone().unwrap_or_else(|| vec![arg_name.clone()]);
+
+            let borrowed = matches!(arg_type, CallbackArgType::Input) && is_borrowed_input(arg);
+            result.push(CallbackArg {
+                name_ident: arg_name,
+                arg_type,
+                field_path,
+                borrowed,
+                unwrapped: false,
+            });
+        }
+        Ok(result)
+    };
+
+    let callback_args = get_callback_args(&function)?;
+    let callback = expand_callback(CallbackDefinition {
+        state_struct: &state_struct,
+        options,
+        sig: &function.sig,
+        generics: &function.sig.generics,
+        callee: quote! { #function_name #turbofish },
+        items_prefix: String::new(),
+        callback_args,
+    })?;
+
+    Ok(quote! {
+        #function
+        #callback
+    })
+}
+
+// A callback function or method, along with the state fields bound to its arguments.
+struct CallbackDefinition<'a> {
+    state_struct: &'a syn::Type,
+    options: CallbackOptions,
+    sig: &'a syn::Signature,
+    generics: &'a syn::Generics,
+    // What the wrapper calls, e.g. `compute_total` or `<State>::compute_total`.
+    callee: proc_macro2::TokenStream,
+    // Start of the generated item names, e.g. `cart_` for the methods of `impl Cart` so that
+    // methods of different states in a module don't clash. Empty for functions.
+    items_prefix: String,
+    callback_args: Vec<CallbackArg>,
+}
+
+// Generates the wrapper running the callback on the state and the `_get_info` function returning
+// its StateCallback, and registers it.
+fn expand_callback(definition: CallbackDefinition) -> syn::Result<proc_macro2::TokenStream> {
+    let CallbackDefinition {
+        state_struct,
+        options: CallbackOptions {
+            guard,
+            inputs: _,
+            outputs: returned_paths,
+        },
+        sig,
+        generics,
+        callee,
+        items_prefix,
+        callback_args,
+    } = definition;
+    let function_name = &sig.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let turbofish = ty_generics.as_turbofish();
 
-                result.push(CallbackArg {
-                    name_ident: arg_name,
-                    arg_type: arg_type,
-                });
+    // Outputs set from the returned value, which can be a single value or a tuple with one value
+    // per output. `Option<T>` returns leave the outputs unchanged on `None`, so an `Option` field
+    // is set to `None` through an `&mut Output<Option<T>>` argument instead. `Result<T, E>`
+    // returns skip the outputs and the callbacks depending on them on `Err`, the same way as a
+    // rejecting guard.
+    let return_type = match sig.output {
+        syn::ReturnType::Default => None,
+        syn::ReturnType::Type(_, ref ty) => Some(ty.as_ref()),
+    };
+    let (returns_result, returns_option) = match return_type {
+        None if returned_paths.is_empty() => (false, false),
+        None => {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "callbacks with `outputs(...)` need to return the values of those outputs",
+            ));
+        }
+        Some(ty) if returned_paths.is_empty() => {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "callbacks returning a value need their output fields listed with `outputs(...)`",
+            ));
+        }
+        Some(ty) => {
+            let result_inner = strip_return_wrapper(ty, "Result");
+            let ty = result_inner.unwrap_or(ty);
+            let option_inner = strip_return_wrapper(ty, "Option");
+            let ty = option_inner.unwrap_or(ty);
+            if option_inner.is_some() && strip_return_wrapper(ty, "Option").is_some() {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "returned `Option`s leave the outputs unchanged on `None`, set `Option` fields \
+                    to `None` through an `&mut Output<Option<T>>` argument instead",
+                ));
             }
+            if returned_paths.len() > 1 {
+                let is_matching_tuple = match ty {
+                    syn::Type::Tuple(tuple) => tuple.elems.len() == returned_paths.len(),
+                    _ => false,
+                };
+                if !is_matching_tuple {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        format!(
+                            "expected a tuple with one value for each of the {} fields in \
+                            `outputs(...)`",
+                            returned_paths.len()
+                        ),
+                    ));
+                }
+            }
+            (result_inner.is_some(), option_inner.is_some())
         }
-        result
     };
+    let returned_outputs: Vec<CallbackArg> = returned_paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| CallbackArg {
+            name_ident: syn::Ident::new(&format!("returned_{}", index), path[0].span()),
+            arg_type: CallbackArgType::Output,
+            field_path: path.clone(),
+            borrowed: false,
+            unwrapped: false,
+        })
+        .collect();
 
-    let callback_args = get_callback_args(&function);
+    let field_path_string = |arg: &CallbackArg| {
+        arg.field_path.iter().map(|ident| ident.to_string()).collect::<Vec<_>>().join(".")
+    };
+    let mut bound_outputs = std::collections::HashSet::new();
+    for arg in callback_args.iter().chain(returned_outputs.iter()) {
+        if let CallbackArgType::Output | CallbackArgType::InOut = arg.arg_type {
+            if !bound_outputs.insert(field_path_string(arg)) {
+                return Err(syn::Error::new(
+                    arg.field_path[0].span(),
+                    format!("field `{}` is bound to more than one output", field_path_string(arg)),
+                ));
+            }
+        }
+    }
+    for arg in callback_args.iter() {
+        if let CallbackArgType::Input = arg.arg_type {
+            if bound_outputs.contains(&field_path_string(arg)) {
+                return Err(syn::Error::new(
+                    arg.field_path[0].span(),
+                    format!(
+                        "field `{}` is both an input and an output, use `&mut InOut<T>` to update \
+                        it in place",
+                        field_path_string(arg)
+                    ),
+                ));
+            }
+        }
+    }
     let inputs: Vec<&CallbackArg> = callback_args
         .iter()
-        .filter_map(|cb| match cb.arg_type {
-            CallbackArgType::Input => Some(cb),
-            _ => None,
-        })
+        .filter(|cb| matches!(cb.arg_type, CallbackArgType::Input))
         .collect();
-    let outputs: Vec<&CallbackArg> = callback_args
+    let argument_outputs: Vec<&CallbackArg> = callback_args
         .iter()
-        .filter_map(|cb| match cb.arg_type {
-            CallbackArgType::Output => Some(cb),
-            _ => None,
-        })
+        .filter(|cb| matches!(cb.arg_type, CallbackArgType::Output))
         .collect();
+    let outputs: Vec<&CallbackArg> =
+        argument_outputs.iter().cloned().chain(returned_outputs.iter()).collect();
+    let in_outs: Vec<&CallbackArg> = callback_args
+        .iter()
+        .filter(|cb| matches!(cb.arg_type, CallbackArgType::InOut))
+        .collect();
+    // Outputs and in-place updates are sent back the same way.
+    let updated_fields: Vec<&CallbackArg> =
+        argument_outputs.iter().chain(in_outs.iter()).cloned().collect();
 
-    let output_variables = outputs.iter().map(|cb| {
+    let output_variables = updated_fields.iter().map(|cb| {
         let name_ident = &cb.name_ident;
-        quote! {
-            let mut #name_ident = Output::new(app.#name_ident.clone());
+        match cb.arg_type {
+            CallbackArgType::InOut => {
+                let read = field_path_read(state_struct, &cb.field_path);
+                quote! {
+                    let mut #name_ident = dust::InOut::new(#read);
+                }
+            }
+            _ => {
+                // Outputs only copy the current value if the callback reads it.
+                let borrow = field_path_borrow(state_struct, &cb.field_path);
+                quote! {
+                    let mut #name_ident = dust::Output::lazy(#borrow);
+                }
+            }
         }
     });
 
@@ -105,36 +529,51 @@ pub fn dust_define_callback(args: TokenStream, input: TokenStream) -> TokenStrea
         let name_ident = &cb.name_ident;
         match cb.arg_type {
             CallbackArgType::Input => {
+                let read = if cb.borrowed {
+                    field_path_borrow(state_struct, &cb.field_path)
+                } else {
+                    field_path_read(state_struct, &cb.field_path)
+                };
+                if cb.unwrapped {
+                    return read;
+                }
                 quote! {
-                    Input {
-                        value: app.#name_ident.clone(),
+                    dust::Input {
+                        value: #read,
                     }
                 }
             }
-            CallbackArgType::Output => {
+            CallbackArgType::Output | CallbackArgType::InOut => {
                 quote! {&mut #name_ident}
             }
+            CallbackArgType::Resource => {
+                // Resolved from the resources registered at startup, never read from the state. A
+                // missing resource fails the callback.
+                quote! {
+                    ::dust::Resource::require()?
+                }
+            }
         }
     });
 
-    let collect_updates = if outputs.len() > 0 {
-        let output_updates = outputs.iter().map(|cb| {
+    // Through the FieldAccess of the state, whose server_only variants only exist on the server.
+    let field_values = |cb: &CallbackArg, value: proc_macro2::TokenStream| {
+        field_path_values(state_struct, &[], &cb.field_path, value)
+    };
+
+    let collect_updates = if !updated_fields.is_empty() {
+        let output_updates = updated_fields.iter().map(|cb| {
             let name_ident = &cb.name_ident;
-            let enum_ident = field_to_enum(name_ident);
+            let values = field_values(cb, quote! { value });
             quote! {
-                match #name_ident.state {
-                    dust::OutputState::NoChange => None,
-                    dust::OutputState::Updated => Some(
-                        <#state_struct as dust::StateTypes>::Value::#enum_ident(#name_ident.value.clone())
-                    ),
-                }
+                #name_ident.into_update().map(|value| #values).unwrap_or_default()
             }
         });
 
         quote! {
             vec![
                 #(#output_updates,)*
-            ].iter().filter_map(|x| { x.clone() }).collect()
+            ].into_iter().flatten().collect()
         }
     } else {
         quote! {
@@ -142,57 +581,312 @@ pub fn dust_define_callback(args: TokenStream, input: TokenStream) -> TokenStrea
         }
     };
 
-    let wrapper_name = syn::Ident::new(&format!("{}_wrapper", function_name), function_name.span());
+    let wrapper_name = syn::Ident::new(
+        &format!("{}{}_wrapper", items_prefix, function_name),
+        function_name.span(),
+    );
+    let call = quote! {
+        #callee (#(
+            #call_args,
+        )*)
+    };
+    let wrapper_body = if returned_outputs.is_empty() {
+        quote! {
+            #call;
+            return Ok(#collect_updates);
+        }
+    } else {
+        let returned_names = returned_outputs.iter().map(|cb| &cb.name_ident).collect::<Vec<_>>();
+        let returned_pattern = if returned_names.len() == 1 {
+            quote! { #(#returned_names)* }
+        } else {
+            quote! { (#(#returned_names,)*) }
+        };
+        let returned_updates = returned_outputs.iter().map(|cb| {
+            let values = field_values(cb, cb.name_ident.to_token_stream());
+            quote! {
+                updates.extend(#values);
+            }
+        });
+        let unwrap_result = if returns_result {
+            quote! {
+                let result = result.map_err(|error| error.to_string())?;
+            }
+        } else {
+            quote! {}
+        };
+        let push_returned = if returns_option {
+            quote! {
+                if let Some(#returned_pattern) = result {
+                    #(#returned_updates)*
+                }
+            }
+        } else {
+            quote! {
+                let #returned_pattern = result;
+                #(#returned_updates)*
+            }
+        };
+        quote! {
+            let result = #call;
+            #unwrap_result
+            let mut updates: Vec<<#state_struct as dust::StateTypes>::Value> = #collect_updates;
+            #push_returned
+            return Ok(updates);
+        }
+    };
+    // Client builds still borrow the outputs, which keeps their fields read.
+    let output_borrows = updated_fields
+        .iter()
+        .map(|cb| field_path_borrow(state_struct, &cb.field_path));
+    // Values of server_only fields only exist in server builds, where callbacks run.
     let wrapper = quote! {
-        fn #wrapper_name(app: &mut #state_struct) -> Vec<<#state_struct as dust::StateTypes>::Value> {
+        #[cfg(feature = "ssr")]
+        fn #wrapper_name #impl_generics (
+            app: &mut #state_struct
+        ) -> Result<Vec<<#state_struct as dust::StateTypes>::Value>, String> #where_clause {
             #(#output_variables)*
-            #function_name(#(
-                #call_args,
-            )*);
-            return #collect_updates;
+            #wrapper_body
+        }
+
+        #[cfg(not(feature = "ssr"))]
+        fn #wrapper_name #impl_generics (
+            app: &mut #state_struct
+        ) -> Result<Vec<<#state_struct as dust::StateTypes>::Value>, String> #where_clause {
+            // Keeps the callback used in client builds.
+            let _ = #callee;
+            #(let _ = #output_borrows;)*
+            return Err("callbacks only run on the server".to_string());
         }
     };
 
-    let input_entries = inputs.iter().map(|arg| {
-        let input_ident = &arg.name_ident;
-        let input_enum = field_to_enum(input_ident);
+    let identifiers = |args: &[&CallbackArg]| {
+        if args.is_empty() {
+            return quote! { Vec::new() };
+        }
+        let entries = args
+            .iter()
+            .map(|arg| field_path_identifiers(state_struct, &[], &arg.field_path));
         quote! {
-            <#state_struct as dust::StateTypes>::Identifier::#input_enum
+            vec![#(#entries,)*].into_iter().flatten().collect()
         }
-    });
-    let output_entries = outputs.iter().map(|arg| {
-        let output_ident = &arg.name_ident;
-        let output_enum = field_to_enum(output_ident);
+    };
+    let input_identifiers = identifiers(&inputs);
+    let output_identifiers = identifiers(&outputs);
+    let with_in_outs = if in_outs.is_empty() {
+        quote! {}
+    } else {
+        let in_out_identifiers = identifiers(&in_outs);
         quote! {
-            <#state_struct as dust::StateTypes>::Identifier::#output_enum
+            .with_in_outs(#in_out_identifiers)
         }
-    });
+    };
 
-    let get_info_name =
-        syn::Ident::new(&format!("{}_get_info", function_name), function_name.span());
+    let get_info_name = syn::Ident::new(
+        &format!("{}{}_get_info", items_prefix, function_name),
+        function_name.span(),
+    );
     let function_name_str = format!("{}", function_name);
+    let with_guard = guard.map(|guard| {
+        quote! {
+            .with_guard(#guard)
+        }
+    });
 
     let get_info_fn = quote! {
-        fn #get_info_name() ->  <#state_struct as dust::StateTypes>::CallbackInfo{
-            <#state_struct as dust::StateTypes>::CallbackInfo::new(
+        // Spelled out as a StateCallback (instead of the CallbackInfo associated type) so the
+        // generic parameters of the callback can be inferred from the state it's registered on.
+        fn #get_info_name #impl_generics () -> dust::StateCallback<
+            <#state_struct as dust::StateTypes>::Identifier,
+            <#state_struct as dust::StateTypes>::Value,
+            #state_struct
+        > #where_clause {
+            dust::StateCallback::new(
                 #function_name_str,
-                #wrapper_name,
-                vec![#(#input_entries,)*],
-                vec![#(#output_entries,)*],
+                #wrapper_name #turbofish,
+                #input_identifiers,
+                #output_identifiers,
             )
-            // <State as dust::StateTypes>::CallbackInfo {
-            //     name: #function_name_str,
-            //     cb: #wrapper_name,
-            //     inputs: vec![#(#input_entries,)*],
-            //     outputs: vec![#(#output_entries,)*],
-            // }
+            .with_module_path(module_path!())
+            #with_in_outs #with_guard
         }
     };
 
-    quote! {
-        #function
+    // Callbacks of non-generic states register themselves, generic ones need to be listed in
+    // #[dust_register_callback(...)] on the state.
+    let is_generic_state = match state_struct {
+        syn::Type::Path(ref path) => path
+            .path
+            .segments
+            .iter()
+            .any(|segment| !segment.arguments.is_empty()),
+        _ => true,
+    };
+    let submit = if generics.params.is_empty() && !is_generic_state {
+        quote! {
+            dust::inventory::submit! {
+                <#state_struct as dust::CallbackRegistry>::Registration::new(#get_info_name)
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
         #wrapper
         #get_info_fn
+        #submit
+    })
+}
+
+// `#[dust_define_callbacks]` on an `impl State` block turns its methods marked with
+// `#[dust_callback(inputs(...), outputs(...))]` into callbacks. The methods take the values of the
+// fields in `inputs(...)` as their arguments (by value or by reference), optionally followed by
+// `Resource<T>` arguments, and return the values of the fields in `outputs(...)`. Other methods of
+// the block are left untouched. The methods of generic states are listed on the state as
+// `#[dust_register_callback(State::method)]`.
+pub fn dust_define_callbacks(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(
+            proc_macro2::TokenStream::from(args).span(),
+            "`#[dust_define_callbacks]` doesn't take arguments",
+        )
+        .to_compile_error()
+        .into();
     }
-    .into()
-}
\ No newline at end of file
+    let item_impl = parse_macro_input!(input as syn::ItemImpl);
+    return expand_define_callbacks(item_impl)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into();
+}
+
+fn expand_define_callbacks(mut item_impl: syn::ItemImpl) -> syn::Result<proc_macro2::TokenStream> {
+    if let Some((_, ref path, _)) = item_impl.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "`#[dust_define_callbacks]` goes on an inherent `impl State` block",
+        ));
+    }
+    let state_struct = item_impl.self_ty.as_ref().clone();
+    let mut callbacks = Vec::new();
+    for item in item_impl.items.iter_mut() {
+        let syn::ImplItem::Fn(method) = item else {
+            continue;
+        };
+        let mut options = None;
+        for attr in method.attrs.iter() {
+            if !attr.path().is_ident("dust_callback") {
+                continue;
+            }
+            if options.is_some() {
+                return Err(syn::Error::new_spanned(attr, "duplicate `#[dust_callback]` attribute"));
+            }
+            options = Some(match attr.meta {
+                syn::Meta::Path(_) => CallbackOptions::default(),
+                _ => attr.parse_args_with(|input: syn::parse::ParseStream| {
+                    CallbackOptions::parse(input, true)
+                })?,
+            });
+        }
+        method.attrs.retain(|attr| !attr.path().is_ident("dust_callback"));
+        let Some(options) = options else {
+            continue;
+        };
+        callbacks.push(expand_callback_method(
+            &state_struct,
+            &item_impl.generics,
+            method,
+            options,
+        )?);
+    }
+
+    Ok(quote! {
+        #item_impl
+        #(#callbacks)*
+    })
+}
+
+fn expand_callback_method(
+    state_struct: &syn::Type,
+    generics: &syn::Generics,
+    method: &syn::ImplItemFn,
+    options: CallbackOptions,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &method.sig;
+    if let Some(receiver) = sig.receiver() {
+        return Err(syn::Error::new_spanned(
+            receiver,
+            "callback methods can't take `self`, list the fields they read in `inputs(...)`",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "callback methods can't be generic, the type parameters of the state come from the \
+            impl block",
+        ));
+    }
+    if sig.inputs.len() < options.inputs.len() {
+        return Err(syn::Error::new_spanned(
+            sig,
+            format!(
+                "expected one argument for each of the {} fields in `inputs(...)`",
+                options.inputs.len()
+            ),
+        ));
+    }
+
+    let mut callback_args = Vec::new();
+    for (index, arg) in sig.inputs.iter().enumerate() {
+        let syn::FnArg::Typed(pat_type) = arg else {
+            continue;
+        };
+        let name_ident = syn::Ident::new(&format!("arg_{}", index), arg.span());
+        if let Some(field_path) = options.inputs.get(index) {
+            callback_args.push(CallbackArg {
+                name_ident,
+                arg_type: CallbackArgType::Input,
+                field_path: field_path.clone(),
+                borrowed: matches!(*pat_type.ty, syn::Type::Reference(_)),
+                unwrapped: true,
+            });
+            continue;
+        }
+        let Ok(CallbackArgType::Resource) = get_arg_type(arg) else {
+            return Err(syn::Error::new_spanned(
+                &pat_type.ty,
+                "arguments of callback methods after the fields in `inputs(...)` can only be \
+                `Resource<T>`",
+            ));
+        };
+        callback_args.push(CallbackArg {
+            name_ident: name_ident.clone(),
+            arg_type: CallbackArgType::Resource,
+            field_path: vec![name_ident],
+            borrowed: false,
+            unwrapped: false,
+        });
+    }
+
+    let state_name = match state_struct {
+        syn::Type::Path(ref path) if path.qself.is_none() => {
+            &path.path.segments.last().unwrap().ident
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                state_struct,
+                "`#[dust_define_callbacks]` goes on an `impl State` block naming the state type",
+            ));
+        }
+    };
+    let method_name = &sig.ident;
+    return expand_callback(CallbackDefinition {
+        state_struct,
+        options,
+        sig,
+        generics,
+        callee: quote! { <#state_struct>::#method_name },
+        items_prefix: format!("{}_", state_name.to_string().to_case(Case::Snake)),
+        callback_args,
+    })
+}
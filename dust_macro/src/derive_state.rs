@@ -1,8 +1,9 @@
 use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned, ToTokens};
 use syn::spanned::Spanned;
 use syn::{parse_macro_input, DeriveInput};
+use crate::computed::computed_macro_ident;
 use crate::enum_utils::field_to_enum;
 use std::collections::HashSet;
 use once_cell::sync::Lazy;
@@ -11,60 +12,553 @@ static INCREMENTABLE_TYPES: Lazy<HashSet<&'static str>> = Lazy::new(|| {
     HashSet::from(["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64"])
 });
 
+static FLOAT_TYPES: Lazy<HashSet<&'static str>> = Lazy::new(|| HashSet::from(["f32", "f64"]));
+
+// Items defined in the internal module of the state, which would shadow the user types of the same
+// name in the field types.
+static GENERATED_ITEMS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    HashSet::from([
+        "Identifier",
+        "Value",
+        "FieldAccess",
+        "DustContext",
+        "ContextInternalState",
+        "CallbackRegistration",
+    ])
+});
+
+// Returns the first name of a generated item a type refers to unqualified, e.g. `Value` in
+// `Vec<Value>` but not in `crate::model::Value`.
+fn find_generated_item(tokens: proc_macro2::TokenStream) -> Option<proc_macro2::Ident> {
+    let mut after_path_separator = false;
+    for token in tokens {
+        match token {
+            proc_macro2::TokenTree::Group(group) => {
+                if let Some(ident) = find_generated_item(group.stream()) {
+                    return Some(ident);
+                }
+                after_path_separator = false;
+            }
+            proc_macro2::TokenTree::Ident(ident) => {
+                if !after_path_separator && GENERATED_ITEMS.contains(ident.to_string().as_str()) {
+                    return Some(ident);
+                }
+                after_path_separator = false;
+            }
+            proc_macro2::TokenTree::Punct(punct) => {
+                after_path_separator = punct.as_char() == ':';
+            }
+            proc_macro2::TokenTree::Literal(_) => {
+                after_path_separator = false;
+            }
+        }
+    }
+    return None;
+}
+
+// Name of the type the helpers of a field are picked by: the last segment of its path, so that
+// `std::string::String` is a String, or None for types with generic arguments, references, etc.
+fn type_name(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(ref path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+    let segment = path.path.segments.last()?;
+    if !segment.arguments.is_empty() {
+        return None;
+    }
+    Some(segment.ident.to_string())
+}
+
 struct DustStateAttributes {
-    callbacks: Vec<proc_macro2::TokenStream>,
+    // Functions, or `State::method` for methods of a `#[dust_define_callbacks]` impl block.
+    callbacks: Vec<syn::Path>,
+    // Concrete instances of a generic state, e.g. #[dust(instance(DashboardState<Revenue>))].
+    instances: Vec<syn::Type>,
+    // Path of the state's server callback under "/server_callback", e.g.
+    // #[dust(endpoint = "checkout")]. Defaults to the snake case struct name.
+    endpoint: Option<syn::LitStr>,
+    // #[dust(embedded)] on states only used as nested or expanded fields of other states, which
+    // get no executor or server callback of their own.
+    embedded: bool,
 }
 
 impl DustStateAttributes {
-    fn from_input(input: &syn::DeriveInput) -> DustStateAttributes {
+    fn from_input(input: &syn::DeriveInput) -> syn::Result<DustStateAttributes> {
         let mut result = DustStateAttributes {
             callbacks: Vec::new(),
+            instances: Vec::new(),
+            endpoint: None,
+            embedded: false,
         };
         for attr in input.attrs.iter() {
-            if attr.meta.path().segments.len() != 1 {
-                panic!(
-                    "Expecting single path segment on attr path for: {:#?}",
-                    attr
-                );
+            // Attributes from other macros (serde, doc comments, ...) are left alone.
+            if attr.path().is_ident("dust_register_callback") {
+                let callbacks = attr.parse_args_with(
+                    syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+                )?;
+                result.callbacks.extend(callbacks);
+            }
+            if attr.path().is_ident("dust") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("instance") {
+                        let content;
+                        syn::parenthesized!(content in meta.input);
+                        let instances = syn::punctuated::Punctuated::<syn::Type, syn::Token![,]>
+                            ::parse_terminated(&content)?;
+                        result.instances.extend(instances);
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("endpoint") {
+                        let endpoint = meta.value()?.parse::<syn::LitStr>()?;
+                        let path = endpoint.value();
+                        let path = path.trim_matches('/');
+                        if path.is_empty() || !path
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '/')
+                        {
+                            return Err(syn::Error::new_spanned(
+                                &endpoint,
+                                "endpoint must be a non-empty path made of letters, digits, `_`, `-` \
+                                and `/`",
+                            ));
+                        }
+                        result.endpoint = Some(endpoint);
+                        return Ok(());
+                    }
+                    if meta.path.is_ident("embedded") {
+                        result.embedded = true;
+                        return Ok(());
+                    }
+                    Err(meta.error(
+                        "unsupported dust attribute, expected `instance(...)`, `endpoint = \"...\"` \
+                        or `embedded`",
+                    ))
+                })?;
             }
+        }
+        if let (true, Some(endpoint)) = (result.embedded, &result.endpoint) {
+            return Err(syn::Error::new_spanned(
+                endpoint,
+                "embedded states have no endpoint, their updates go through the state embedding \
+                them",
+            ));
+        }
+        return Ok(result);
+    }
+}
+
+#[derive(Default)]
+struct DustFieldAttributes {
+    server_only: bool,
+    client_only: bool,
+    // The field is a DustState itself, whose fields and callbacks are merged into this state.
+    nested: bool,
+    // The field is a struct deriving DustState with #[dust(embedded)] whose fields get their own
+    // identifiers, so callbacks can depend on paths like `filters.country`. Its callbacks aren't
+    // merged.
+    expand: bool,
+    // #[dust(computed = compute_total)], the #[dust_computed] function setting the field from the
+    // fields it reads.
+    computed: Option<syn::Path>,
+    // Constraints checked by the DustContext setters, and on the server for the values sent by
+    // clients: #[dust(range(0..=100))], #[dust(max_len = 64)] and #[dust(validate = check_email)]
+    // with `fn check_email(value: &String) -> Result<(), E>`. `max_len` counts the characters of
+    // strings and the elements of collections (see `dust::Length`).
+    range: Option<syn::Expr>,
+    max_len: Option<syn::Expr>,
+    validate: Option<syn::Path>,
+    // #[dust(step = 5)], the amount added and subtracted by the increment and decrement handlers
+    // of an integer field. Defaults to 1.
+    step: Option<syn::Expr>,
+    // #[dust(select)] on a field taking one of a set of values, typically an enum. It gets
+    // handlers selecting a value, and parsing the value of a <select> with `FromStr`.
+    select: bool,
+}
 
-            if format!("{}", attr.meta.path().segments[0].ident) == "dust_register_callback" {
-                result
-                    .callbacks
-                    .push(attr.meta.require_list().unwrap().tokens.clone());
+impl DustFieldAttributes {
+    fn from_field(field: &syn::Field) -> syn::Result<DustFieldAttributes> {
+        let mut result = DustFieldAttributes::default();
+        for attr in field.attrs.iter() {
+            if !attr.path().is_ident("dust") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("server_only") {
+                    result.server_only = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("client_only") {
+                    result.client_only = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("nested") {
+                    result.nested = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("expand") {
+                    result.expand = true;
+                    return Ok(());
+                }
+                if meta.path.is_ident("computed") {
+                    result.computed = Some(meta.value()?.parse::<syn::Path>()?);
+                    return Ok(());
+                }
+                if meta.path.is_ident("range") {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    result.range = Some(content.parse::<syn::Expr>()?);
+                    return Ok(());
+                }
+                if meta.path.is_ident("max_len") {
+                    result.max_len = Some(meta.value()?.parse::<syn::Expr>()?);
+                    return Ok(());
+                }
+                if meta.path.is_ident("validate") {
+                    result.validate = Some(meta.value()?.parse::<syn::Path>()?);
+                    return Ok(());
+                }
+                if meta.path.is_ident("step") {
+                    result.step = Some(meta.value()?.parse::<syn::Expr>()?);
+                    return Ok(());
+                }
+                if meta.path.is_ident("select") {
+                    result.select = true;
+                    return Ok(());
+                }
+                Err(meta.error(
+                    "unsupported dust field attribute, expected `server_only`, `client_only`, \
+                    `nested`, `expand`, `computed = ...`, `range(...)`, `max_len = ...`, \
+                    `validate = ...`, `step = ...` or `select`",
+                ))
+            })?;
+            if result.server_only && result.client_only {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "a field can't be both server_only and client_only",
+                ));
+            }
+            if result.nested && (result.server_only || result.client_only || result.expand) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "a nested field can't be server_only, client_only or expand",
+                ));
+            }
+            if result.expand && (result.server_only || result.client_only) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "an expanded field can't be server_only or client_only",
+                ));
+            }
+            if result.computed.is_some() && (result.client_only || result.is_nested()) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "a computed field is set on the server, it can't be client_only, nested or \
+                    expand",
+                ));
+            }
+            if (result.step.is_some() || result.select) && (result.server_only || result.is_nested()) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`step` and `select` configure the handlers of fields with a signal, they \
+                    can't be used on server_only, nested or expanded fields",
+                ));
+            }
+            if result.has_constraints() && result.is_nested() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "nested and expanded fields are validated by the constraints of their own \
+                    fields",
+                ));
             }
         }
-        return result;
+        return Ok(result);
+    }
+
+    // Nested and expanded fields are both represented by the identifiers and values of their
+    // own state.
+    fn is_nested(&self) -> bool {
+        return self.nested || self.expand;
+    }
+
+    fn has_constraints(&self) -> bool {
+        return self.range.is_some() || self.max_len.is_some() || self.validate.is_some();
+    }
+}
+
+// Closure checking a value of the field against its constraints, returning the error message of the
+// first one it violates.
+fn field_validation(field: &StateField) -> Option<proc_macro2::TokenStream> {
+    let attributes = &field.attributes;
+    if !attributes.has_constraints() {
+        return None;
+    }
+    let field_type = &field.field.ty;
+    let range = attributes.range.as_ref().map(|range| {
+        quote! {
+            let range = #range;
+            if !range.contains(value) {
+                return Err(format!("must be within {:?}", range));
+            }
+        }
+    });
+    let max_len = attributes.max_len.as_ref().map(|max_len| {
+        quote! {
+            if ::dust::Length::length(value) > #max_len {
+                return Err(format!("must be at most {} long", #max_len));
+            }
+        }
+    });
+    let validate = attributes.validate.as_ref().map(|validate| {
+        quote! {
+            #validate(value).map_err(|error| error.to_string())?;
+        }
+    });
+    return Some(quote! {
+        |value: &#field_type| -> Result<(), String> {
+            #range
+            #max_len
+            #validate
+            return Ok(());
+        }
+    });
+}
+
+// Server-only fields only have a Value variant in server builds, so their values can't be created
+// or matched on the client.
+fn server_only_cfg(field: &StateField) -> proc_macro2::TokenStream {
+    if field.attributes.server_only {
+        return quote! { #[cfg(feature = "ssr")] };
+    }
+    return quote! {};
+}
+
+// Snake case name of an instance of a generic state from its type arguments, e.g. `revenue` for
+// `Dash<Revenue>` and `revenue_daily` for `Dash<metrics::Revenue, Daily>`.
+fn instance_name(instance: &syn::Type) -> syn::Result<String> {
+    fn collect_idents(tokens: proc_macro2::TokenStream, idents: &mut Vec<String>) {
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                proc_macro2::TokenTree::Group(group) => collect_idents(group.stream(), idents),
+                proc_macro2::TokenTree::Ident(ident) => {
+                    // Only the last segment of paths, `metrics::Revenue` is `revenue`.
+                    let is_path_prefix = matches!(
+                        tokens.peek(),
+                        Some(proc_macro2::TokenTree::Punct(punct)) if punct.as_char() == ':'
+                    );
+                    if !is_path_prefix {
+                        idents.push(ident.to_string().to_case(Case::Snake));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut idents = Vec::new();
+    if let syn::Type::Path(path) = instance {
+        if let Some(segment) = path.path.segments.last() {
+            if let syn::PathArguments::AngleBracketed(ref arguments) = segment.arguments {
+                collect_idents(arguments.args.to_token_stream(), &mut idents);
+            }
+        }
+    }
+    if idents.is_empty() {
+        return Err(syn::Error::new_spanned(
+            instance,
+            "instances are named after their type arguments, e.g. `State<Revenue>`",
+        ));
+    }
+    return Ok(idents.join("_"));
+}
+
+struct StateField<'a> {
+    field: &'a syn::Field,
+    // Name of the generated identifiers (signals, setters, accessors). Fields of tuple structs
+    // are named by position: `field_0`, `field_1`, ...
+    name: syn::Ident,
+    // How the field is accessed on the state struct: `state.name` or `state.0`.
+    member: syn::Member,
+    attributes: DustFieldAttributes,
+}
+
+impl<'a> StateField<'a> {
+    fn from_fields(fields: &'a syn::Fields) -> syn::Result<Vec<StateField<'a>>> {
+        let mut result = Vec::new();
+        for (index, field) in fields.iter().enumerate() {
+            let (name, member) = match field.ident {
+                Some(ref ident) => (ident.clone(), syn::Member::Named(ident.clone())),
+                None => (
+                    syn::Ident::new(&format!("field_{}", index), field.ty.span()),
+                    syn::Member::Unnamed(syn::Index {
+                        index: index as u32,
+                        span: field.ty.span(),
+                    }),
+                ),
+            };
+            let attributes = DustFieldAttributes::from_field(field)?;
+            if let Some(ident) = find_generated_item(field.ty.to_token_stream()) {
+                return Err(syn::Error::new_spanned(
+                    ident.clone(),
+                    format!(
+                        "field types can't be named `{}` like an item generated for the state, \
+                        use a path (e.g. `crate::module::{}`) or rename it on import",
+                        ident, ident
+                    ),
+                ));
+            }
+            if let Some(ref step) = attributes.step {
+                let is_integer = type_name(&field.ty)
+                    .is_some_and(|name| INCREMENTABLE_TYPES.contains(name.as_str()));
+                if !is_integer {
+                    return Err(syn::Error::new_spanned(
+                        step,
+                        "`step` is only supported on integer fields",
+                    ));
+                }
+            }
+            result.push(StateField {
+                field,
+                name,
+                member,
+                attributes,
+            });
+        }
+        return Ok(result);
     }
 }
 
 pub fn derive_state(input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
-    let attributes = DustStateAttributes::from_input(&ast);
+    return expand_derive_state(&ast)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into();
+}
+
+fn expand_derive_state(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let attributes = DustStateAttributes::from_input(ast)?;
 
     let state_struct = &ast.ident;
     let internal_mod = syn::Ident::new(
-        format!("{}Internal", state_struct).as_str().to_case(Case::Snake).as_str(), 
+        format!("{}Internal", state_struct).as_str().to_case(Case::Snake).as_str(),
         state_struct.span()
     );
 
-    let fields = if let syn::Data::Struct(syn::DataStruct {
-        fields: syn::Fields::Named(syn::FieldsNamed { ref named, .. }),
-        ..
-    }) = ast.data
-    {
-        named
+    let fields = match ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: ref fields @ (syn::Fields::Named(_) | syn::Fields::Unnamed(_)),
+            ..
+        }) => StateField::from_fields(fields)?,
+        syn::Data::Struct(_) => {
+            return Err(syn::Error::new_spanned(
+                state_struct,
+                "DustState can't be derived for unit structs",
+            ));
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                state_struct,
+                "DustState can only be derived for structs",
+            ));
+        }
+    };
+
+    //
+    // Generics
+    //
+    if let Some(lifetime) = ast.generics.lifetimes().next() {
+        return Err(syn::Error::new_spanned(
+            lifetime,
+            "DustState can't have lifetime parameters",
+        ));
+    }
+    // Signals and the executor require the state (and so its type parameters) to be 'static.
+    let mut generics = ast.generics.clone();
+    for type_param in generics.type_params_mut() {
+        type_param.bounds.push(syn::parse_quote!('static));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let is_generic = !generics.params.is_empty();
+
+    // Generic states are only usable through the concrete instances listed with
+    // #[dust(instance(...))], each of them gets its own executor and server callback.
+    let instances: Vec<syn::Type> = if is_generic {
+        if attributes.instances.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &ast.generics,
+                "generic DustState structs need their concrete instances listed with \
+                #[dust(instance(...))]",
+            ));
+        }
+        attributes.instances.clone()
     } else {
-        unimplemented!();
+        if let Some(instance) = attributes.instances.first() {
+            return Err(syn::Error::new_spanned(
+                instance,
+                "#[dust(instance(...))] is only supported on generic DustState structs",
+            ));
+        }
+        vec![syn::parse_quote!(super::#state_struct)]
     };
 
+    // Server-only fields have no signal in the DustContext and are never sent to the client.
+    let server_only_fields: Vec<&StateField> = fields
+        .iter()
+        .filter(|field| field.attributes.server_only)
+        .collect();
+    // Nested states keep their own signals in a nested DustContext.
+    let nested_fields: Vec<&StateField> = fields
+        .iter()
+        .filter(|field| field.attributes.is_nested())
+        .collect();
+    // Nested contexts are provided by type, so a second field of the same type would replace the
+    // context of the first one. Expanded contexts are only reached through their field, e.g.
+    // `ctx.billing`, so several fields can expand the same type.
+    let mut nested_types: Vec<String> = Vec::new();
+    for field in nested_fields.iter().filter(|field| field.attributes.nested) {
+        let nested_type = field.field.ty.to_token_stream().to_string();
+        if nested_types.contains(&nested_type) {
+            return Err(syn::Error::new_spanned(
+                &field.field.ty,
+                "another nested field has the same type, nested states are provided as contexts \
+                by type so each type can only be nested once",
+            ));
+        }
+        nested_types.push(nested_type);
+    }
+    let client_fields: Vec<&StateField> = fields
+        .iter()
+        .filter(|field| !field.attributes.server_only && !field.attributes.is_nested())
+        .collect();
+    // Client-only fields only exist as signals in the DustContext, they have no Identifier/Value
+    // and never reach the Executor.
+    let state_fields: Vec<&StateField> = fields
+        .iter()
+        .filter(|field| !field.attributes.client_only)
+        .collect();
+    let synced_fields: Vec<&StateField> = client_fields
+        .iter()
+        .cloned()
+        .filter(|field| !field.attributes.client_only)
+        .collect();
+
     //
     // Identifier Enum
     //
-    let identifier_enum_entries = fields.iter().map(|field| {
-        let entry_ident = field_to_enum(&field.ident.clone().unwrap());
-        quote! {
-            #entry_ident
+    let identifier_enum_entries = state_fields.iter().map(|field| {
+        let entry_ident = field_to_enum(&field.name);
+        let entry_inner_type = &field.field.ty;
+        if field.attributes.is_nested() {
+            quote! {
+                #entry_ident(<#entry_inner_type as ::dust::StateTypes>::Identifier)
+            }
+        } else {
+            quote! {
+                #entry_ident
+            }
         }
     });
 
@@ -78,75 +572,393 @@ pub fn derive_state(input: TokenStream) -> TokenStream {
     //
     // Value Enum
     //
-    let value_enum_entries = fields.iter().map(|field| {
-        let entry_ident = field_to_enum(&field.ident.clone().unwrap());
-        let entry_inner_type = &field.ty;
-        quote! {
-            #entry_ident(#entry_inner_type)
+    let value_enum_entries = state_fields.iter().map(|field| {
+        let entry_ident = field_to_enum(&field.name);
+        let entry_inner_type = &field.field.ty;
+        if field.attributes.server_only {
+            quote! {
+                #[cfg(feature = "ssr")]
+                #[serde(skip)]
+                #entry_ident(#entry_inner_type)
+            }
+        } else if field.attributes.is_nested() {
+            quote! {
+                #entry_ident(<#entry_inner_type as ::dust::StateTypes>::Value)
+            }
+        } else {
+            quote! {
+                #entry_ident(#entry_inner_type)
+            }
         }
     });
 
-    let value_to_identifier_match_entries = fields.iter().map(|field| {
-        let entry_ident = field_to_enum(&field.ident.clone().unwrap());
-        quote! {
-            Value::#entry_ident(_) => Identifier::#entry_ident
+    let value_to_identifier_match_entries = state_fields.iter().map(|field| {
+        let entry_ident = field_to_enum(&field.name);
+        let cfg = server_only_cfg(field);
+        if field.attributes.is_nested() {
+            quote! {
+                Value::#entry_ident(v) => Identifier::#entry_ident(v.to_identifier())
+            }
+        } else {
+            quote! {
+                #cfg
+                Value::#entry_ident(_) => Identifier::#entry_ident
+            }
         }
     });
 
-    let identifier_to_value_from_signal_entries = fields.iter().map(|field| {
-        let field_ident = &field.ident;
-        let entry_ident = field_to_enum(&field.ident.clone().unwrap());
+    let identifier_to_value_from_signal_entries = synced_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let entry_ident = field_to_enum(&field.name);
+        quote! {
+            Identifier::#entry_ident => Some(Value::#entry_ident(self.#field_ident.get_untracked()))
+        }
+    });
+    let identifier_to_value_from_signal_server_only_entries = server_only_fields.iter().map(|field| {
+        let entry_ident = field_to_enum(&field.name);
         quote! {
-            Identifier::#entry_ident => Value::#entry_ident(self.#field_ident.get_untracked())
+            Identifier::#entry_ident => None
+        }
+    });
+    let identifier_to_value_from_nested_entries = nested_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let entry_ident = field_to_enum(&field.name);
+        quote! {
+            Identifier::#entry_ident(identifier) => self.#field_ident
+                .get_values_from_identifiers(&std::collections::HashSet::from([identifier]))
+                .into_iter()
+                .next()
+                .map(Value::#entry_ident)
+        }
+    });
+
+    // Serde would otherwise require `T: Deserialize<'de>` for every type parameter, which conflicts
+    // with the `DeserializeOwned` bound generic states declare for their parameters.
+    let value_validate_match_entries = state_fields.iter().map(|field| {
+        let entry_ident = field_to_enum(&field.name);
+        let field_literal = syn::LitStr::new(&field.name.to_string(), field.name.span());
+        if field.attributes.is_nested() {
+            // Errors of nested fields are reported with their path, e.g. `filters.country`.
+            return quote! {
+                Value::#entry_ident(v) => {
+                    if let Err(error) = v.validate() {
+                        return Err(match error {
+                            ::dust::UpdateError::InvalidValue { field, message } => {
+                                ::dust::UpdateError::InvalidValue {
+                                    field: format!("{}.{}", #field_literal, field),
+                                    message,
+                                }
+                            }
+                            error => error,
+                        });
+                    }
+                }
+            };
+        }
+        let cfg = server_only_cfg(field);
+        match field_validation(field) {
+            Some(validation) => quote! {
+                #cfg
+                Value::#entry_ident(v) => {
+                    if let Err(message) = (#validation)(v) {
+                        return Err(::dust::UpdateError::InvalidValue {
+                            field: #field_literal.to_string(),
+                            message,
+                        });
+                    }
+                }
+            },
+            None => quote! {
+                #cfg
+                Value::#entry_ident(_) => {}
+            },
         }
     });
 
+    let serde_bound = if is_generic {
+        quote! { #[serde(bound = "")] }
+    } else {
+        quote! {}
+    };
+
     let dust_value_enum = quote! {
         #[derive(Clone, Debug, ::dust::serde::Serialize, ::dust::serde::Deserialize)]
-        pub enum Value {
+        #serde_bound
+        pub enum Value #impl_generics #where_clause {
             #(#value_enum_entries,)*
         }
 
-        impl ::dust::ValueToIdentifier<Identifier> for Value {
+        impl #impl_generics ::dust::ValueToIdentifier<Identifier> for Value #ty_generics #where_clause {
             fn to_identifier(&self) -> Identifier {
                 match self {
                     #(#value_to_identifier_match_entries,)*
                 }
             }
         }
+
+        impl #impl_generics ::dust::ValidateValue for Value #ty_generics #where_clause {
+            fn validate(&self) -> Result<(), ::dust::UpdateError> {
+                match self {
+                    #(#value_validate_match_entries,)*
+                }
+                return Ok(());
+            }
+        }
+    };
+
+    //
+    // Field Access
+    //
+    let field_access_functions = state_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let field_member = &field.member;
+        let field_type = &field.field.ty;
+        quote! {
+            pub fn #field_ident #impl_generics (
+                state: &super::#state_struct #ty_generics
+            ) -> &#field_type #where_clause {
+                &state.#field_member
+            }
+        }
+    });
+
+    // Identifiers and values of each field, reached through `::dust::field_access_of` when
+    // callbacks are bound to field paths like `filters.country`.
+    let field_path_functions = state_fields.iter().map(|field| {
+        let field_type = &field.field.ty;
+        let entry_ident = field_to_enum(&field.name);
+        let identifier_ident = syn::Ident::new(
+            &format!("{}_identifier", field.name),
+            field.name.span(),
+        );
+        let value_ident = syn::Ident::new(
+            &format!("{}_value", field.name),
+            field.name.span(),
+        );
+        if field.attributes.is_nested() {
+            quote! {
+                pub fn #identifier_ident(
+                    &self, identifier: <#field_type as ::dust::StateTypes>::Identifier
+                ) -> Identifier {
+                    Identifier::#entry_ident(identifier)
+                }
+
+                pub fn #value_ident #impl_generics (
+                    &self, value: <#field_type as ::dust::StateTypes>::Value
+                ) -> Value #ty_generics #where_clause {
+                    Value::#entry_ident(value)
+                }
+            }
+        } else if field.attributes.server_only {
+            // Only callbacks set them, which run on the server.
+            quote! {
+                pub fn #identifier_ident(&self) -> Identifier {
+                    Identifier::#entry_ident
+                }
+
+                #[cfg(feature = "ssr")]
+                pub fn #value_ident #impl_generics (
+                    &self, value: #field_type
+                ) -> Value #ty_generics #where_clause {
+                    Value::#entry_ident(value)
+                }
+            }
+        } else {
+            quote! {
+                pub fn #identifier_ident(&self) -> Identifier {
+                    Identifier::#entry_ident
+                }
+
+                pub fn #value_ident #impl_generics (
+                    &self, value: #field_type
+                ) -> Value #ty_generics #where_clause {
+                    Value::#entry_ident(value)
+                }
+            }
+        }
+    });
+
+    // Callbacks binding a whole nested or expanded field depend on (and update) all of its
+    // sub-fields.
+    let whole_field_functions = state_fields.iter().map(|field| {
+        let field_type = &field.field.ty;
+        let entry_ident = field_to_enum(&field.name);
+        let identifiers_ident = syn::Ident::new(
+            &format!("{}_identifiers", field.name),
+            field.name.span(),
+        );
+        let values_ident = syn::Ident::new(&format!("{}_values", field.name), field.name.span());
+        let value_ident = syn::Ident::new(&format!("{}_value", field.name), field.name.span());
+        if field.attributes.is_nested() {
+            quote! {
+                pub fn #identifiers_ident(&self) -> Vec<Identifier> {
+                    <<#field_type as ::dust::StateTypes>::FieldAccess>::default()
+                        .identifiers_of_state()
+                        .into_iter()
+                        .map(Identifier::#entry_ident)
+                        .collect()
+                }
+
+                pub fn #values_ident #impl_generics (
+                    &self, value: #field_type
+                ) -> Vec<Value #ty_generics> #where_clause {
+                    <<#field_type as ::dust::StateTypes>::FieldAccess>::default()
+                        .values_of_state(value)
+                        .into_iter()
+                        .map(Value::#entry_ident)
+                        .collect()
+                }
+            }
+        } else {
+            let server_only_cfg = server_only_cfg(field);
+            quote! {
+                pub fn #identifiers_ident(&self) -> Vec<Identifier> {
+                    vec![Identifier::#entry_ident]
+                }
+
+                #server_only_cfg
+                pub fn #values_ident #impl_generics (
+                    &self, value: #field_type
+                ) -> Vec<Value #ty_generics> #where_clause {
+                    vec![self.#value_ident(value)]
+                }
+            }
+        }
+    });
+    let state_identifiers = state_fields.iter().map(|field| {
+        syn::Ident::new(&format!("{}_identifiers", field.name), field.name.span())
+    });
+    let state_values = state_fields.iter().map(|field| {
+        let values_ident = syn::Ident::new(&format!("{}_values", field.name), field.name.span());
+        let field_member = &field.member;
+        if field.attributes.server_only {
+            // Server-only values are never sent to clients.
+            return quote! {
+                #[cfg(feature = "ssr")]
+                values.extend(self.#values_ident(state.#field_member));
+            };
+        }
+        quote! {
+            values.extend(self.#values_ident(state.#field_member));
+        }
+    });
+
+    let dust_field_access = quote! {
+        #[derive(Default)]
+        pub struct FieldAccess;
+
+        impl FieldAccess {
+            #(#field_access_functions)*
+
+            #(#field_path_functions)*
+
+            #(#whole_field_functions)*
+
+            // Identifiers of all the fields, for callbacks binding this state as a nested field.
+            pub fn identifiers_of_state(&self) -> Vec<Identifier> {
+                let mut identifiers = Vec::new();
+                #(identifiers.extend(self.#state_identifiers());)*
+                return identifiers;
+            }
+
+            pub fn values_of_state #impl_generics (
+                &self, state: super::#state_struct #ty_generics
+            ) -> Vec<Value #ty_generics> #where_clause {
+                let mut values = Vec::new();
+                #(#state_values)*
+                return values;
+            }
+        }
     };
 
     //
     // Context
     //
-    let signal_fields = fields.iter().map(|field| {
-        let field_ident = &field.ident;
+    let signal_fields = client_fields.iter().map(|field| {
+        let field_ident = &field.name;
         let signal_write_ident = syn::Ident::new(
-            &format!("{}_write_signal", field_ident.clone().unwrap()),
+            &format!("{}_write_signal", field_ident),
             field_ident.span(),
         );
-        let field_type = &field.ty;
+        let field_type = &field.field.ty;
         quote! {
             pub #field_ident: ::dust::leptos::ReadSignal<#field_type>,
             #signal_write_ident: ::dust::leptos::WriteSignal<#field_type>,
         }
     });
 
-    let signal_variables_definition = fields.iter().map(|field| {
-        let field_ident = &field.ident;
+    // Fields with constraints get a signal with the error of the last value rejected by their
+    // setter, cleared once a valid value is set.
+    let validated_fields: Vec<&StateField> = client_fields
+        .iter()
+        .cloned()
+        .filter(|field| field.attributes.has_constraints())
+        .collect();
+    let error_signal_idents = |field: &StateField| {
+        let error_ident = syn::Ident::new(&format!("{}_error", field.name), field.name.span());
+        let error_write_ident = syn::Ident::new(
+            &format!("{}_error_write_signal", field.name),
+            field.name.span(),
+        );
+        (error_ident, error_write_ident)
+    };
+    let error_signal_fields = validated_fields.iter().map(|field| {
+        let (error_ident, error_write_ident) = error_signal_idents(field);
+        quote! {
+            pub #error_ident: ::dust::leptos::ReadSignal<Option<String>>,
+            #error_write_ident: ::dust::leptos::WriteSignal<Option<String>>,
+        }
+    });
+    let error_signal_variables_definition = validated_fields.iter().map(|field| {
+        let (error_ident, error_write_ident) = error_signal_idents(field);
+        quote! {
+            let (#error_ident, #error_write_ident) = ::dust::leptos::create_signal(None);
+        }
+    });
+    let error_signal_fields_initialization = validated_fields.iter().map(|field| {
+        let (error_ident, error_write_ident) = error_signal_idents(field);
+        quote! {
+            #error_ident: #error_ident,
+            #error_write_ident: #error_write_ident,
+        }
+    });
+
+    let field_error_setters = validated_fields.iter().map(|field| {
+        let (_, error_write_ident) = error_signal_idents(field);
+        let field_literal = syn::LitStr::new(&field.name.to_string(), field.name.span());
+        quote! {
+            if field == #field_literal {
+                self.#error_write_ident.set(Some(message));
+                return true;
+            }
+        }
+    });
+    let nested_field_error_setters = nested_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let prefix = syn::LitStr::new(&format!("{}.", field.name), field.name.span());
+        quote! {
+            if let Some(field) = field.strip_prefix(#prefix) {
+                return self.#field_ident.set_field_error(field, message);
+            }
+        }
+    });
+
+    let signal_variables_definition = client_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let field_member = &field.member;
         let signal_write_ident = syn::Ident::new(
-            &format!("{}_write_signal", field_ident.clone().unwrap()),
+            &format!("{}_write_signal", field_ident),
             field_ident.span(),
         );
         quote! {
-            let (#field_ident, #signal_write_ident) = ::dust::leptos::create_signal(state.#field_ident);
+            let (#field_ident, #signal_write_ident) = ::dust::leptos::create_signal(state.#field_member);
         }
     });
 
-    let signal_fields_initialization = fields.iter().map(|field| {
-        let field_ident = &field.ident;
+    let signal_fields_initialization = client_fields.iter().map(|field| {
+        let field_ident = &field.name;
         let signal_write_ident = syn::Ident::new(
-            &format!("{}_write_signal", field_ident.clone().unwrap()),
+            &format!("{}_write_signal", field_ident),
             field_ident.span(),
         );
         quote! {
@@ -155,17 +967,17 @@ pub fn derive_state(input: TokenStream) -> TokenStream {
         }
     });
 
-    let signal_fields_update = fields.iter().map(|field| {
-        let field_ident = &field.ident;
+    let signal_fields_update = synced_fields.iter().map(|field| {
+        let field_ident = &field.name;
         let signal_write_ident = syn::Ident::new(
-            &format!("{}_write_signal", field_ident.clone().unwrap()),
+            &format!("{}_write_signal", field_ident),
             field_ident.span(),
         );
         let field_literal = syn::LitStr::new(
-            &format!("{}", field_ident.clone().unwrap()),
+            &format!("{}", field_ident),
             field_ident.span(),
         );
-        let enum_ident = field_to_enum(&field.ident.clone().unwrap());
+        let enum_ident = field_to_enum(&field.name);
         quote! {
             Value::#enum_ident(v) => {
                 ::dust::leptos::logging::log!("apply_updates setting {}", #field_literal);
@@ -174,103 +986,422 @@ pub fn derive_state(input: TokenStream) -> TokenStream {
         }
     });
 
-    let signal_fields_setter_getter = fields.iter().map(|field| {
-        let field_ident = &field.ident;
-        let field_type = &field.ty;
+    let signal_fields_setter_getter = client_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let field_type = &field.field.ty;
         // let getter_ident = syn::Ident::new(
-        //     &format!("get_{}", field_ident.clone().unwrap()),
+        //     &format!("get_{}", field_ident),
         //     field_ident.span(),
         // );
         let setter_ident = syn::Ident::new(
-            &format!("set_{}", field_ident.clone().unwrap()),
+            &format!("set_{}", field_ident),
             field_ident.span(),
         );
         let update_ident = syn::Ident::new(
-            &format!("update_{}", field_ident.clone().unwrap()),
+            &format!("update_{}", field_ident),
             field_ident.span(),
         );
         let signal_write_ident = syn::Ident::new(
-            &format!("{}_write_signal", field_ident.clone().unwrap()),
+            &format!("{}_write_signal", field_ident),
             field_ident.span(),
         );
-        let enum_ident = field_to_enum(&field.ident.clone().unwrap());
+        let enum_ident = field_to_enum(&field.name);
+        let handle_updates = if field.attributes.client_only {
+            quote! {}
+        } else {
+            quote! {
+                self.handle_updates(vec![Value::#enum_ident(self.#field_ident.get_untracked())]);
+            }
+        };
 
-        use quote::ToTokens;
-        let increment_onclick = if INCREMENTABLE_TYPES.contains(field_type.clone().into_token_stream().to_string().as_str())  {
+        // Event handlers ready for `view!` attributes, picked from the type of the field.
+        let field_type_name = type_name(field_type).unwrap_or_default();
+        let bind_ident = syn::Ident::new(&format!("bind_{}", field_ident), field_ident.span());
+        // Values that fail to parse are reported in the error signal of validated fields, and
+        // ignored otherwise.
+        let on_parse_error = |message: &str| {
+            if field.attributes.has_constraints() {
+                let (_, error_write_ident) = error_signal_idents(field);
+                quote! {
+                    state.#error_write_ident.set(Some(#message.to_string()));
+                }
+            } else {
+                quote! {}
+            }
+        };
+        let parse_bind = |message: &str| {
+            let on_parse_error = on_parse_error(message);
+            quote! {
+                pub fn #bind_ident(self: &std::rc::Rc<Self>) -> impl Fn(::dust::web_sys::Event) {
+                    let state = self.clone();
+                    return move |event| {
+                        match ::dust::leptos::event_target_value(&event).parse::<#field_type>() {
+                            Ok(v) => state.#setter_ident(v),
+                            Err(_) => {
+                                #on_parse_error
+                            }
+                        }
+                    };
+                }
+            }
+        };
+        let event_handlers = if INCREMENTABLE_TYPES.contains(field_type_name.as_str()) {
+            let increment_ident =
+                syn::Ident::new(&format!("increment_{}", field_ident), field_ident.span());
+            let decrement_ident =
+                syn::Ident::new(&format!("decrement_{}", field_ident), field_ident.span());
             let increment_onclick_ident = syn::Ident::new(
-                &format!("increment_onclick_{}", field_ident.clone().unwrap()),
+                &format!("increment_onclick_{}", field_ident),
+                field_ident.span(),
+            );
+            let decrement_onclick_ident = syn::Ident::new(
+                &format!("decrement_onclick_{}", field_ident),
                 field_ident.span(),
             );
+            let step = match field.attributes.step {
+                Some(ref step) => quote! { #step },
+                None => quote! { 1 },
+            };
+            let bind = parse_bind("must be a whole number");
             quote! {
+                // Adds the step, stopping at the largest value of the type.
+                pub fn #increment_ident(self: &std::rc::Rc<Self>) {
+                    self.#update_ident(|x| {
+                        *x = x.saturating_add(#step);
+                    });
+                }
+
+                // Subtracts the step, stopping at the smallest value of the type.
+                pub fn #decrement_ident(self: &std::rc::Rc<Self>) {
+                    self.#update_ident(|x| {
+                        *x = x.saturating_sub(#step);
+                    });
+                }
+
                 pub fn #increment_onclick_ident(self: &std::rc::Rc<Self>) -> impl Fn(::dust::web_sys::MouseEvent) {
+                    let state = self.clone();
+                    return move |_| state.#increment_ident();
+                }
+
+                pub fn #decrement_onclick_ident(self: &std::rc::Rc<Self>) -> impl Fn(::dust::web_sys::MouseEvent) {
+                    let state = self.clone();
+                    return move |_| state.#decrement_ident();
+                }
+
+                #bind
+            }
+        } else if FLOAT_TYPES.contains(field_type_name.as_str()) {
+            parse_bind("must be a number")
+        } else if field_type_name == "String" {
+            // Set once typing pauses, each update can trigger a server callback.
+            quote! {
+                pub fn #bind_ident(self: &std::rc::Rc<Self>) -> impl Fn(::dust::web_sys::Event) {
+                    let state = self.clone();
+                    let set = ::dust::components::debounced(
+                        ::dust::components::TEXT_INPUT_DEBOUNCE,
+                        move |value: String| state.#setter_ident(value),
+                    );
+                    return move |event| {
+                        set(::dust::leptos::event_target_value(&event));
+                    };
+                }
+            }
+        } else if field_type_name == "bool" {
+            let toggle_onclick_ident = syn::Ident::new(
+                &format!("toggle_onclick_{}", field_ident),
+                field_ident.span(),
+            );
+            quote! {
+                pub fn #toggle_onclick_ident(self: &std::rc::Rc<Self>) -> impl Fn(::dust::web_sys::MouseEvent) {
                     let state = self.clone();
                     return move |_| {
                         state.#update_ident(|x| {
-                            *x = *x + 1;
+                            *x = !*x;
                         });
                     };
                 }
+
+                // For the change events of checkboxes.
+                pub fn #bind_ident(self: &std::rc::Rc<Self>) -> impl Fn(::dust::web_sys::Event) {
+                    let state = self.clone();
+                    return move |event| {
+                        state.#setter_ident(::dust::leptos::event_target_checked(&event));
+                    };
+                }
+            }
+        } else if field.attributes.select {
+            let select_onclick_ident = syn::Ident::new(
+                &format!("select_onclick_{}", field_ident),
+                field_ident.span(),
+            );
+            let bind = parse_bind("is not a valid option");
+            quote! {
+                pub fn #select_onclick_ident(
+                    self: &std::rc::Rc<Self>,
+                    value: #field_type,
+                ) -> impl Fn(::dust::web_sys::MouseEvent) {
+                    let state = self.clone();
+                    return move |_| {
+                        state.#setter_ident(value.clone());
+                    };
+                }
+
+                // For the change events of <select> elements, whose option values are parsed with
+                // `FromStr`.
+                #bind
             }
         } else {
             quote! {}
         };
 
-        quote! {
-            pub fn #setter_ident(self: &std::rc::Rc<Self>, v: #field_type) {
-                self.#signal_write_ident.set(v);
-                self.handle_updates(vec![Value::#enum_ident(self.#field_ident.get_untracked())]);
+        let setters = match field_validation(field) {
+            // Invalid values are rejected, so they never reach the signal or the server.
+            Some(validation) => {
+                let (_, error_write_ident) = error_signal_idents(field);
+                quote! {
+                    pub fn #setter_ident(self: &std::rc::Rc<Self>, v: #field_type) {
+                        if let Err(message) = (#validation)(&v) {
+                            self.#error_write_ident.set(Some(message));
+                            return;
+                        }
+                        self.#error_write_ident.set(None);
+                        self.#signal_write_ident.set(v);
+                        #handle_updates
+                    }
+
+                    pub fn #update_ident(self: &std::rc::Rc<Self>, f: impl FnOnce(&mut #field_type)) {
+                        let mut v = self.#field_ident.get_untracked();
+                        f(&mut v);
+                        self.#setter_ident(v);
+                    }
+                }
             }
+            None => quote! {
+                pub fn #setter_ident(self: &std::rc::Rc<Self>, v: #field_type) {
+                    self.#signal_write_ident.set(v);
+                    #handle_updates
+                }
 
-            pub fn #update_ident(self: &std::rc::Rc<Self>, f: impl FnOnce(&mut #field_type)) {
-                self.#signal_write_ident.update(f);
-                self.handle_updates(vec![Value::#enum_ident(self.#field_ident.get_untracked())]);
+                pub fn #update_ident(self: &std::rc::Rc<Self>, f: impl FnOnce(&mut #field_type)) {
+                    self.#signal_write_ident.update(f);
+                    #handle_updates
+                }
+            },
+        };
+
+        let field_fn_ident = syn::Ident::new(&format!("field_{}", field_ident), field_ident.span());
+        let with_error = if field.attributes.has_constraints() {
+            let (error_ident, _) = error_signal_idents(field);
+            quote! {
+                .with_error(self.#error_ident.into())
             }
+        } else {
+            quote! {}
+        };
+
+        quote! {
+            #setters
 
-            #increment_onclick
+            #event_handlers
 
+            // The field bound to its setter, for the widgets of ::dust::components.
+            pub fn #field_fn_ident(self: &std::rc::Rc<Self>) -> ::dust::components::Field<#field_type> {
+                let state = self.clone();
+                return ::dust::components::Field::new(
+                    self.#field_ident.into(),
+                    move |v| state.#setter_ident(v),
+                )
+                .with_loading(self.loading())
+                #with_error;
+            }
+        }
+    });
+
+    let signal_fields_update_server_only = server_only_fields.iter().map(|field| {
+        let enum_ident = field_to_enum(&field.name);
+        quote! {
+            #[cfg(feature = "ssr")]
+            Value::#enum_ident(_) => {}
+        }
+    });
+
+    let nested_context_fields = nested_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let field_type = &field.field.ty;
+        quote! {
+            pub #field_ident: <#field_type as ::dust::StateTypes>::Context,
+        }
+    });
+
+    // Input updates of nested contexts are forwarded to this context, whose executor knows about
+    // the nested callbacks.
+    let nested_context_definitions = nested_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let field_member = &field.member;
+        let field_type = &field.field.ty;
+        let enum_ident = field_to_enum(&field.name);
+        quote! {
+            let #field_ident = <#field_type>::create_nested_context(state.#field_member, {
+                let this = this.clone();
+                ::dust::UpdateForwarder::new(
+                    move |updates: Vec<<#field_type as ::dust::StateTypes>::Value>| {
+                        if let Some(this) = this.upgrade() {
+                            this.handle_updates(updates.into_iter().map(Value::#enum_ident).collect());
+                        }
+                    }
+                ).with_loading(loading)
+            });
         }
     });
 
+    let nested_context_initialization = nested_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        quote! {
+            #field_ident: #field_ident,
+        }
+    });
+
+    let nested_context_update = nested_fields.iter().map(|field| {
+        let field_ident = &field.name;
+        let enum_ident = field_to_enum(&field.name);
+        quote! {
+            Value::#enum_ident(v) => {
+                self.#field_ident.apply_updates(vec![v]);
+            }
+        }
+    });
+
+    let nested_context_provide = nested_fields.iter().filter(|field| field.attributes.nested).map(|field| {
+        let field_ident = &field.name;
+        quote! {
+            ::dust::leptos::provide_context(self.#field_ident.clone());
+            self.#field_ident.provide_nested_contexts();
+        }
+    });
+
+    // The context methods talking to the server need the executor and server callback of the
+    // concrete instance. The associated types are spelled out so they still normalize to the
+    // generated types under the bound.
+    let mut instance_generics = generics.clone();
+    instance_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(
+            super::#state_struct #ty_generics: ::dust::StateInstance<
+                Identifier = Identifier,
+                Value = Value #ty_generics,
+            >
+        ));
+    let instance_where_clause = &instance_generics.where_clause;
+    let executor = quote! {
+        <super::#state_struct #ty_generics as ::dust::StateInstance>::executor()
+    };
+
     let dust_context = quote! {
         #[derive(Clone, Debug)]
         struct ContextInternalState {
             initialized: std::cell::Cell<bool>,
+            // Signed form of the callback outputs received from the server.
+            signed_state: std::cell::RefCell<std::collections::HashMap<Identifier, ::dust::SignedValue>>,
+            // Number of server callbacks in flight.
+            pending_requests: ::dust::leptos::RwSignal<usize>,
+            // Whether this context (or the parent context of a nested one) waits for the server.
+            loading: ::dust::leptos::Signal<bool>,
         }
 
         #[derive(Clone, Debug)]
-        pub struct DustContext {
+        pub struct DustContext #impl_generics #where_clause {
             #(#signal_fields)*
+            #(#error_signal_fields)*
+            #(#nested_context_fields)*
 
             context_internal_state: ContextInternalState,
+            update_forwarder: ::dust::UpdateForwarder<Value #ty_generics>,
+            state_type: std::marker::PhantomData<fn() -> super::#state_struct #ty_generics>,
         }
 
-        impl DustContext {
-            pub fn from_default_state() -> Self {
-                let state = super::#state_struct::default();
-                #(#signal_variables_definition)*
-                Self {
-                    #(#signal_fields_initialization)*
+        impl #impl_generics DustContext #ty_generics #instance_where_clause {
+            pub fn from_default_state() -> std::rc::Rc<Self> {
+                return Self::from_state(
+                    <super::#state_struct #ty_generics>::default(),
+                    ::dust::UpdateForwarder::none(),
+                );
+            }
 
-                    context_internal_state: ContextInternalState {
-                        initialized: std::cell::Cell::new(false),
-                    },
-                }
+            pub fn from_state(
+                state: super::#state_struct #ty_generics,
+                update_forwarder: ::dust::UpdateForwarder<Value #ty_generics>,
+            ) -> std::rc::Rc<Self> {
+                let pending_requests = ::dust::leptos::create_rw_signal(0);
+                let loading = update_forwarder.loading().unwrap_or_else(|| {
+                    ::dust::leptos::Signal::derive(move || pending_requests.get() > 0)
+                });
+                return std::rc::Rc::new_cyclic(|this: &std::rc::Weak<Self>| {
+                    #(#nested_context_definitions)*
+                    #(#signal_variables_definition)*
+                    #(#error_signal_variables_definition)*
+                    Self {
+                        #(#signal_fields_initialization)*
+                        #(#error_signal_fields_initialization)*
+                        #(#nested_context_initialization)*
+
+                        context_internal_state: ContextInternalState {
+                            initialized: std::cell::Cell::new(false),
+                            signed_state: std::cell::RefCell::new(std::collections::HashMap::new()),
+                            pending_requests,
+                            loading,
+                        },
+                        update_forwarder,
+                        state_type: std::marker::PhantomData,
+                    }
+                });
+            }
+
+            // True while the server processes updates of this context, e.g. to show widgets as
+            // loading.
+            pub fn loading(&self) -> ::dust::leptos::Signal<bool> {
+                return self.context_internal_state.loading;
+            }
+
+            pub fn provide_nested_contexts(&self) {
+                #(#nested_context_provide)*
             }
 
             pub fn get_values_from_identifiers(
                 &self, identifiers: &std::collections::HashSet<Identifier>
-            ) -> Vec<Value> {
-                identifiers.iter().map(|value_ident| {
+            ) -> Vec<Value #ty_generics> {
+                identifiers.iter().filter_map(|value_ident| {
                     match *value_ident {
                         #(#identifier_to_value_from_signal_entries,)*
+                        #(#identifier_to_value_from_signal_server_only_entries,)*
+                        #(#identifier_to_value_from_nested_entries,)*
                     }
                 }).collect()
             }
 
-            pub fn apply_updates(&self, updates: Vec<Value>) {
+            // Shows an error the server reported for a value on the error signal of its field,
+            // e.g. `filters.country`. Returns false for fields without one.
+            pub fn set_field_error(&self, field: &str, message: String) -> bool {
+                #(#field_error_setters)*
+                #(#nested_field_error_setters)*
+                return false;
+            }
+
+            pub fn apply_signed_updates(&self, updates: Vec<(Value #ty_generics, ::dust::SignedValue)>) {
+                let mut values = Vec::new();
+                for (value, signed) in updates {
+                    self.context_internal_state.signed_state.borrow_mut().insert(value.to_identifier(), signed);
+                    values.push(value);
+                }
+                self.apply_updates(values);
+            }
+
+            pub fn apply_updates(&self, updates: Vec<Value #ty_generics>) {
                 for update in updates {
                     match update {
                         #(#signal_fields_update,)*
+                        #(#signal_fields_update_server_only,)*
+                        #(#nested_context_update,)*
                     }
                 }
             }
@@ -280,18 +1411,52 @@ pub fn derive_state(input: TokenStream) -> TokenStream {
             pub fn initialize_state(self: &std::rc::Rc<Self>){
                 ::dust::leptos::logging::log!("initialize_state");
                 self.context_internal_state.initialized.set(true);
+                let executor = match #executor {
+                    Ok(executor) => executor,
+                    Err(error) => {
+                        ::dust::leptos::logging::log!("initialize_state error: {}", error);
+                        return;
+                    }
+                };
                 self.handle_updates(
-                    self.get_values_from_identifiers(
-                        &EXECUTOR.get_required_initialization_inputs()
-                    )
+                    self.get_values_from_identifiers(&executor.get_required_initialization_inputs())
                 );
             }
 
-            pub fn handle_updates(self: &std::rc::Rc<Self>, input_updates: Vec<Value>) {
-                let updated_inputs = input_updates.iter().map(|v| v.to_identifier()).collect();
-                let execution_plan = EXECUTOR.get_execution_plan(&updated_inputs);
-                let required_state = EXECUTOR.get_required_state(&updated_inputs, &execution_plan);
-                let required_state_values = self.get_values_from_identifiers(&required_state);
+            pub fn handle_updates(self: &std::rc::Rc<Self>, input_updates: Vec<Value #ty_generics>) {
+                // Nested contexts leave the updates to the parent state.
+                let input_updates = match self.update_forwarder.forward(input_updates) {
+                    Some(input_updates) => input_updates,
+                    None => return,
+                };
+                let executor = match #executor {
+                    Ok(executor) => executor,
+                    Err(error) => {
+                        ::dust::leptos::logging::log!("server_callback error: {}", error);
+                        return;
+                    }
+                };
+                let updated_inputs = input_updates.iter().map(|v| v.to_identifier()).collect::<Vec<_>>();
+                let execution_plan = executor.get_execution_plan(&updated_inputs);
+                if execution_plan.is_empty() {
+                    // No callback depends on the updated inputs, there's nothing to compute.
+                    return;
+                }
+                let required_state = executor.get_required_state(&updated_inputs, &execution_plan);
+
+                // Callback outputs can only be sent back in the signed form issued by the server.
+                let (signed_identifiers, unsigned_identifiers): (
+                    std::collections::HashSet<Identifier>,
+                    std::collections::HashSet<Identifier>,
+                ) = required_state.into_iter().partition(|identifier| executor.is_output(identifier));
+                let required_state_values = self.get_values_from_identifiers(&unsigned_identifiers);
+                let signed_state: Vec<::dust::SignedValue> = {
+                    let signed_state = self.context_internal_state.signed_state.borrow();
+                    signed_identifiers
+                        .iter()
+                        .filter_map(|identifier| signed_state.get(identifier).cloned())
+                        .collect()
+                };
 
                 ::dust::leptos::logging::log!("handle_updates call");
                 ::dust::leptos::logging::log!("  input_updates: {:?}", input_updates);
@@ -299,12 +1464,25 @@ pub fn derive_state(input: TokenStream) -> TokenStream {
                 ::dust::leptos::logging::log!("  required_state_values: {:?}", required_state_values);
 
                 let state = self.clone();
+                let pending_requests = self.context_internal_state.pending_requests;
+                pending_requests.update(|pending| *pending += 1);
                 ::dust::leptos::spawn_local(async move {
-                    let response = server_callback(input_updates, required_state_values).await;
+                    let response = <super::#state_struct #ty_generics as ::dust::StateInstance>::server_callback(
+                        input_updates, required_state_values, signed_state
+                    ).await;
+                    pending_requests.update(|pending| *pending -= 1);
                     match response {
-                        Ok(output_updates) => {
-                            ::dust::leptos::logging::log!("    server output_updates: {:?}", output_updates);
-                            state.apply_updates(output_updates);
+                        Ok(response) => {
+                            ::dust::leptos::logging::log!("    server output_updates: {:?}", response.updates);
+                            state.apply_signed_updates(response.updates);
+                            for error in response.errors {
+                                if let ::dust::UpdateError::InvalidValue { field, message } = &error {
+                                    if state.set_field_error(field, message.clone()) {
+                                        continue;
+                                    }
+                                }
+                                ::dust::leptos::logging::log!("server_callback error: {}", error);
+                            }
                         }
                         Err(e) => {
                             ::dust::leptos::logging::log!("server_callback error: {}", e);
@@ -316,96 +1494,360 @@ pub fn derive_state(input: TokenStream) -> TokenStream {
         }
     };
 
-    //
-    // CallbackInfo
-    //
-    let dust_callback_info = quote! {
-        #[derive(Clone)]
-        pub struct CallbackInfo {
-            pub name: &'static str,
-            pub cb: fn(&mut super::#state_struct) -> Vec<Value>,
-            pub inputs: Vec<Identifier>,
-            pub outputs: Vec<Identifier>,
+    // `compute` refers to `compute_get_info`, and `Cart::compute` (or `Self::compute`) to the
+    // `cart_compute_get_info` generated for the method by dust_define_callbacks.
+    let registered_callbacks = attributes.callbacks.iter().map(|callback_path| {
+        let mut path = callback_path.clone();
+        let callback_ident = path.segments.pop().unwrap().into_value().ident;
+        let items_prefix = match path.segments.pop() {
+            Some(state_segment) if state_segment.value().ident == "Self" => {
+                format!("{}_", state_struct.to_string().to_case(Case::Snake))
+            }
+            Some(state_segment) => {
+                format!("{}_", state_segment.value().ident.to_string().to_case(Case::Snake))
+            }
+            None => String::new(),
+        };
+        let callback_get_info_ident = syn::Ident::new(
+            &format!("{}{}_get_info", items_prefix, callback_ident),
+            callback_ident.span(),
+        );
+
+        quote! {
+            #path #callback_get_info_ident()
         }
+    });
+
+    // Callbacks of non-generic states register themselves from dust_define_callback. The ones
+    // listed in #[dust_register_callback(...)] come first, in their listed order.
+    let (dust_callback_registration, collect_registered_callbacks) = if is_generic {
+        (quote! {}, quote! {})
+    } else {
+        let registration = quote! {
+            pub struct CallbackRegistration {
+                pub get_info: fn() -> ::dust::StateCallback<Identifier, Value, super::#state_struct>,
+            }
 
-        impl CallbackInfo {
-            pub fn new(
-                name: &'static str,
-                cb: fn(&mut super::#state_struct) -> Vec<Value>,
-                inputs: Vec<Identifier>,
-                outputs: Vec<Identifier>
-            ) -> CallbackInfo {
-                CallbackInfo{
-                    name, cb, inputs, outputs
+            impl CallbackRegistration {
+                pub const fn new(
+                    get_info: fn() -> ::dust::StateCallback<Identifier, Value, super::#state_struct>,
+                ) -> CallbackRegistration {
+                    CallbackRegistration { get_info }
                 }
             }
-        }
+
+            ::dust::inventory::collect!(CallbackRegistration);
+
+            impl ::dust::CallbackRegistry for super::#state_struct {
+                type Registration = CallbackRegistration;
+            }
+        };
+        let collect = quote! {
+            let mut collected: Vec<_> = ::dust::inventory::iter::<#internal_mod::CallbackRegistration>
+                .into_iter()
+                .map(|registration| (registration.get_info)())
+                .filter(|callback| {
+                    !callbacks.iter().any(|listed| {
+                        listed.module_path == callback.module_path && listed.name == callback.name
+                    })
+                })
+                .collect();
+            // The link-time collection order isn't stable.
+            collected.sort_by(|a, b| (a.module_path, &a.name).cmp(&(b.module_path, &b.name)));
+            callbacks.extend(collected);
+        };
+        (registration, collect)
     };
 
-    let registered_callbacks = attributes.callbacks.iter().map(|callback_tokens| {
-        let callback_get_info_ident = syn::Ident::new(
-            &format!("{}_get_info", callback_tokens),
-            callback_tokens.span(),
+    // Computed fields get a callback defined by the macro that comes with their #[dust_computed]
+    // function. It registers itself like any other callback of the state.
+    let mut computed_callbacks = Vec::new();
+    for field in state_fields.iter() {
+        let Some(ref function) = field.attributes.computed else {
+            continue;
+        };
+        if is_generic {
+            return Err(syn::Error::new_spanned(
+                function,
+                "computed fields aren't supported on generic states, define their callback with \
+                #[dust_define_callback(...)]",
+            ));
+        }
+        let mut computed_macro = function.clone();
+        if let Some(last) = computed_macro.segments.last_mut() {
+            last.ident = computed_macro_ident(&last.ident);
+        }
+        let field_name = &field.name;
+        let callback_name = syn::Ident::new(
+            &format!("{}_{}_computed", state_struct.to_string().to_case(Case::Snake), field_name),
+            field_name.span(),
         );
+        computed_callbacks.push(quote! {
+            #computed_macro!(#callback_name, #function, #state_struct, #field_name);
+        });
+    }
 
-        quote! {
-            #callback_get_info_ident()
+    //
+    // Apply Updates
+    //
+    let apply_updates_enum_update_match = state_fields.iter().map(|field| {
+        // eprintln!("field: {:#?}", field);
+        let field_member = &field.member;
+        let enum_ident = field_to_enum(&field.name);
+        if field.attributes.is_nested() {
+            quote! {
+                #internal_mod::Value::#enum_ident(v) => {
+                    ::dust::ApplyUpdates::apply_updates(&mut self.#field_member, std::slice::from_ref(v));
+                }
+            }
+        } else {
+            let cfg = server_only_cfg(field);
+            quote! {
+                #cfg
+                #internal_mod::Value::#enum_ident(v) => {self.#field_member = v.clone();}
+            }
         }
     });
 
     //
-    // Apply Updates
+    // Nested States
     //
-    let apply_updates_enum_update_match = fields.iter().map(|field| {
-        // eprintln!("field: {:#?}", field);
-        let field_ident = &field.ident;
-        let enum_ident = field_to_enum(&field.ident.clone().unwrap());
+    let server_only_identifiers = server_only_fields.iter().map(|field| {
+        let enum_ident = field_to_enum(&field.name);
         quote! {
-            #internal_mod::Value::#enum_ident(v) => {self.#field_ident = v.clone();}
+            #internal_mod::Identifier::#enum_ident
+        }
+    });
+    let nested_server_only_identifiers = nested_fields.iter().map(|field| {
+        let field_type = &field.field.ty;
+        let enum_ident = field_to_enum(&field.name);
+        quote! {
+            identifiers.extend(
+                <#field_type>::get_server_only_identifiers()
+                    .into_iter()
+                    .map(#internal_mod::Identifier::#enum_ident)
+            );
+        }
+    });
+    let nested_callbacks = nested_fields.iter().filter(|field| field.attributes.nested).map(|field| {
+        let field_member = &field.member;
+        let field_type = &field.field.ty;
+        let field_literal = syn::LitStr::new(&format!("{}", field.name), field.name.span());
+        let enum_ident = field_to_enum(&field.name);
+        quote! {
+            callbacks.extend(<#field_type>::get_all_callbacks().into_iter().map(|callback| {
+                callback.nest(
+                    #field_literal,
+                    |state: &mut Self| &mut state.#field_member,
+                    #internal_mod::Identifier::#enum_ident,
+                    #internal_mod::Value::#enum_ident,
+                )
+            }));
         }
     });
 
-    quote! {
-        mod #internal_mod {
-            use ::dust::*;  // Get traits in scope.
-            use ::dust::leptos::*;  // Get traits in scope.
-
-            #dust_identifier_enum
-
-            #dust_value_enum
-
-            #dust_context
-
-            #dust_callback_info
+    //
+    // Instances
+    //
+    // Every state (and every instance of a generic state) gets its own server callback endpoint,
+    // so several states can live in the same application.
+    let endpoint_base = match attributes.endpoint {
+        Some(ref endpoint) => endpoint.value().trim_matches('/').to_string(),
+        None => state_struct.to_string().to_case(Case::Snake),
+    };
+    // Instances of generic states are named after their type arguments, e.g. `Dash<Revenue>` gets
+    // EXECUTOR_REVENUE and the "/dash_revenue" endpoint, so reordering them keeps their URLs.
+    let mut instance_suffixes: Vec<String> = Vec::new();
+    for instance in instances.iter() {
+        let suffix = if is_generic { format!("_{}", instance_name(instance)?) } else { String::new() };
+        if instance_suffixes.contains(&suffix) {
+            return Err(syn::Error::new_spanned(
+                instance,
+                format!("another instance of the state is also named `{}`", &suffix[1..]),
+            ));
+        }
+        instance_suffixes.push(suffix);
+    }
+    let dust_instances = instances.iter().zip(instance_suffixes.iter()).map(|(instance, suffix)| {
+        let endpoint = syn::LitStr::new(
+            &format!("/{}{}", endpoint_base, suffix),
+            instance.span(),
+        );
+        let endpoint_path = syn::LitStr::new(
+            &format!("/server_callback/{}{}", endpoint_base, suffix),
+            instance.span(),
+        );
+        let instance_name = if is_generic {
+            instance.to_token_stream().to_string().replace(' ', "")
+        } else {
+            state_struct.to_string()
+        };
+        let executor_ident = syn::Ident::new(
+            &format!("EXECUTOR{}", suffix.to_uppercase()),
+            instance.span(),
+        );
+        let server_callback_ident =
+            syn::Ident::new(&format!("server_callback{}", suffix), instance.span());
+        let server_callback_struct_ident = syn::Ident::new(
+            &format!("ServerCallback{}", suffix.to_case(Case::UpperCamel)),
+            instance.span(),
+        );
+        if attributes.embedded {
+            // The updates of embedded contexts are forwarded to the state embedding them.
+            return quote! {
+                impl ::dust::StateInstance for #instance {
+                    fn executor() -> Result<&'static ::dust::Executor<
+                        Identifier, <#instance as ::dust::StateTypes>::Value, #instance
+                    >, String> {
+                        Err(format!("{} is embedded and has no executor", #instance_name))
+                    }
 
-            pub static EXECUTOR: ::dust::once_cell::sync::Lazy<
-                ::dust::Executor<Identifier, Value, super::#state_struct>,
-            > = ::dust::once_cell::sync::Lazy::new(|| {
+                    fn server_callback(
+                        _input_updates: Vec<<#instance as ::dust::StateTypes>::Value>,
+                        _required_state: Vec<<#instance as ::dust::StateTypes>::Value>,
+                        _signed_state: Vec<::dust::SignedValue>,
+                    ) -> ::dust::ServerCallbackFuture<<#instance as ::dust::StateTypes>::Value> {
+                        Box::pin(async {
+                            Err(::dust::leptos::ServerFnError::new(format!(
+                                "{} is embedded and has no server callback", #instance_name
+                            )))
+                        })
+                    }
+                }
+            };
+        }
+        quote! {
+            pub static #executor_ident: ::dust::once_cell::sync::Lazy<Result<
+                ::dust::Executor<Identifier, <#instance as ::dust::StateTypes>::Value, #instance>,
+                String,
+            >> = ::dust::once_cell::sync::Lazy::new(|| {
                 let mut app = ::dust::Executor::new();
-                for callback in super::#state_struct::get_registered_callbacks() {
+                for identifier in <#instance>::get_server_only_identifiers() {
+                    app.register_server_only(identifier);
+                }
+                for callback in <#instance>::get_all_callbacks() {
                     app.register_callback(callback);
                 }
-                app.init_callbacks();
-                app
+                app.init_callbacks()?;
+                Ok(app)
             });
 
-            #[::leptos::server(ServerCallback, "/server_callback", "Cbor")]
-            pub async fn server_callback(
-                input_updates: Vec<Value>,
-                required_state: Vec<Value>,
-            ) -> Result<Vec<Value>, ::dust::leptos::ServerFnError> {
+            ::dust::inventory::submit! {
+                ::dust::ServerCallbackEndpoint {
+                    path: #endpoint_path,
+                    state: #instance_name,
+                    check: ::dust::check_executor::<#instance>,
+                }
+            }
+
+            #[::leptos::server(#server_callback_struct_ident, "/server_callback", "Cbor", #endpoint)]
+            pub async fn #server_callback_ident(
+                input_updates: Vec<<#instance as ::dust::StateTypes>::Value>,
+                required_state: Vec<<#instance as ::dust::StateTypes>::Value>,
+                signed_state: Vec<::dust::SignedValue>,
+            ) -> Result<
+                ::dust::UpdateResponse<<#instance as ::dust::StateTypes>::Value>,
+                ::dust::leptos::ServerFnError,
+            > {
                 println!(
                     "server_callback input_updates: {:?} required_state {:?}",
                     input_updates, required_state
                 );
 
-                let output_updates = EXECUTOR.process_updates(input_updates, required_state);
-                Ok(output_updates)
+                // Guards are checked against the request, so callbacks don't run without one.
+                let Some(request) = ::dust::leptos::use_context::<::dust::http::request::Parts>() else {
+                    return Err(::dust::leptos::ServerFnError::new(
+                        "the request isn't available to check the callback guards",
+                    ));
+                };
+                let executor = #executor_ident
+                    .as_ref()
+                    .map_err(|error| ::dust::leptos::ServerFnError::new(error))?;
+                let response =
+                    executor.process_updates(&request, input_updates, required_state, signed_state);
+                match response {
+                    Ok(response) => Ok(response),
+                    // Rejected values are shown on the error signal of their field by the client.
+                    Err(error @ ::dust::UpdateError::InvalidValue { .. }) => {
+                        Ok(::dust::UpdateResponse::from_errors(vec![error]))
+                    }
+                    Err(error) => Err(::dust::leptos::ServerFnError::new(error)),
+                }
             }
+
+            impl ::dust::StateInstance for #instance {
+                fn executor() -> Result<&'static ::dust::Executor<
+                    Identifier, <#instance as ::dust::StateTypes>::Value, #instance
+                >, String> {
+                    #executor_ident.as_ref().map_err(|error| error.clone())
+                }
+
+                fn server_callback(
+                    input_updates: Vec<<#instance as ::dust::StateTypes>::Value>,
+                    required_state: Vec<<#instance as ::dust::StateTypes>::Value>,
+                    signed_state: Vec<::dust::SignedValue>,
+                ) -> ::dust::ServerCallbackFuture<<#instance as ::dust::StateTypes>::Value> {
+                    Box::pin(#server_callback_ident(input_updates, required_state, signed_state))
+                }
+            }
+        }
+    });
+
+    let embedded = attributes.embedded;
+    // Expanded fields don't merge the callbacks of their state, which mustn't have its own
+    // endpoint either. The check needs concrete field types.
+    let expanded_field_checks = fields
+        .iter()
+        .filter(|field| field.attributes.expand && !is_generic)
+        .map(|field| {
+            let field_type = &field.field.ty;
+            quote_spanned! {field_type.span()=>
+                const _: () = assert!(
+                    <#field_type as ::dust::StateTypes>::EMBEDDED,
+                    "expanded fields need a state with #[dust(embedded)]",
+                );
+            }
+        });
+
+    let mut outer_instance_generics = generics.clone();
+    outer_instance_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(
+            #state_struct #ty_generics: ::dust::StateInstance<
+                Identifier = #internal_mod::Identifier,
+                Value = #internal_mod::Value #ty_generics,
+            >
+        ));
+    let outer_instance_where_clause = &outer_instance_generics.where_clause;
+
+    Ok(quote! {
+        mod #internal_mod {
+            // Only the traits the generated code calls methods of, so that glob imports don't
+            // clash with the items of the state's module.
+            use ::dust::leptos::{SignalGet as _, SignalGetUntracked as _, SignalSet as _, SignalUpdate as _};
+            use ::dust::ValueToIdentifier as _;
+            use super::*;  // Get the field types in scope.
+
+            #dust_identifier_enum
+
+            #dust_value_enum
+
+            #dust_field_access
+
+            #dust_context
+
+            #dust_callback_registration
+
+            #(#dust_instances)*
         }
 
-        impl ::dust::ApplyUpdates<#internal_mod::Value> for #state_struct {
-            fn apply_updates(&mut self, updates: &Vec<#internal_mod::Value>) {
+        #(#computed_callbacks)*
+
+        #(#expanded_field_checks)*
+
+        impl #impl_generics ::dust::ApplyUpdates<#internal_mod::Value #ty_generics> for #state_struct #ty_generics #where_clause {
+            fn apply_updates(&mut self, updates: &[#internal_mod::Value #ty_generics]) {
                 for update in updates.iter() {
                     match update {
                         #(#apply_updates_enum_update_match,)*
@@ -414,38 +1856,75 @@ pub fn derive_state(input: TokenStream) -> TokenStream {
             }
         }
 
-        impl #state_struct {
-            pub fn get_registered_callbacks() -> Vec<::dust::StateCallback<#internal_mod::Identifier, #internal_mod::Value, #state_struct>> {
-                return vec![#(#registered_callbacks,)*];
+        impl #impl_generics #state_struct #ty_generics #where_clause {
+            pub fn get_registered_callbacks() -> Vec<::dust::StateCallback<
+                #internal_mod::Identifier,
+                #internal_mod::Value #ty_generics,
+                #state_struct #ty_generics
+            >> {
+                let mut callbacks: Vec<::dust::StateCallback<
+                    #internal_mod::Identifier,
+                    #internal_mod::Value #ty_generics,
+                    #state_struct #ty_generics
+                >> = vec![#(#registered_callbacks,)*];
+                #collect_registered_callbacks
+                return callbacks;
+            }
+
+            // Registered callbacks along with the ones of nested states.
+            pub fn get_all_callbacks() -> Vec<::dust::StateCallback<
+                #internal_mod::Identifier,
+                #internal_mod::Value #ty_generics,
+                #state_struct #ty_generics
+            >> {
+                let mut callbacks = Self::get_registered_callbacks();
+                #(#nested_callbacks)*
+                return callbacks;
+            }
+
+            pub fn get_server_only_identifiers() -> Vec<#internal_mod::Identifier> {
+                let mut identifiers = vec![#(#server_only_identifiers,)*];
+                #(#nested_server_only_identifiers)*
+                return identifiers;
             }
         }
 
-        impl ::dust::StateTypes for #state_struct {
+        impl #impl_generics ::dust::StateTypes for #state_struct #ty_generics #where_clause {
             // Associated type definition
             type Identifier = #internal_mod::Identifier;
-            type Value = #internal_mod::Value;
+            type Value = #internal_mod::Value #ty_generics;
             type CallbackInfo = ::dust::StateCallback<
-                #internal_mod::Identifier, 
-                #internal_mod::Value, 
-                #state_struct
+                #internal_mod::Identifier,
+                #internal_mod::Value #ty_generics,
+                #state_struct #ty_generics
             >;
-            type Context = std::rc::Rc<#internal_mod::DustContext>;
+            type Context = std::rc::Rc<#internal_mod::DustContext #ty_generics>;
+            type FieldAccess = #internal_mod::FieldAccess;
+            const EMBEDDED: bool = #embedded;
         }
 
-        impl #state_struct {
+        impl #impl_generics #state_struct #ty_generics #outer_instance_where_clause {
             pub fn provide_and_initiaze_context() {
-                let state = std::rc::Rc::new(#internal_mod::DustContext::from_default_state());
+                let state = <#internal_mod::DustContext #ty_generics>::from_default_state();
                 ::dust::leptos::provide_context(state.clone());
+                state.provide_nested_contexts();
                 ::dust::leptos::create_effect(move |_| {
-                    log!("Initializing state...");
+                    ::dust::leptos::logging::log!("Initializing state...");
                     state.initialize_state();
                 });
             }
 
-            pub fn expect_context() -> std::rc::Rc<#internal_mod::DustContext> {
-                return ::dust::leptos::expect_context::<std::rc::Rc<#internal_mod::DustContext>>();
+            // Context of this state when it's nested in another state, which processes its updates.
+            pub fn create_nested_context(
+                self,
+                update_forwarder: ::dust::UpdateForwarder<#internal_mod::Value #ty_generics>,
+            ) -> std::rc::Rc<#internal_mod::DustContext #ty_generics> {
+                return <#internal_mod::DustContext #ty_generics>::from_state(self, update_forwarder);
+            }
+
+            pub fn expect_context() -> std::rc::Rc<#internal_mod::DustContext #ty_generics> {
+                return ::dust::leptos::expect_context::<std::rc::Rc<#internal_mod::DustContext #ty_generics>>();
             }
         }
-    }
-    .into()
+    })
 }
@@ -1,14 +1,46 @@
 use proc_macro::TokenStream;
 use quote::quote;
 
+// Accepts `dust_main!(App)` or `dust_main!(App, resources = init_resources)`, where
+// `init_resources` is an async fn returning `dust::Resources`.
+struct DustMainArgs {
+    app: syn::Path,
+    resources: Option<syn::Path>,
+}
+
+impl syn::parse::Parse for DustMainArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let app = input.parse::<syn::Path>()?;
+        let mut resources = None;
+        if input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            let key = input.parse::<syn::Ident>()?;
+            if key != "resources" {
+                return Err(syn::Error::new(key.span(), "expected `resources = <fn>`"));
+            }
+            input.parse::<syn::Token![=]>()?;
+            resources = Some(input.parse::<syn::Path>()?);
+        }
+        Ok(DustMainArgs { app, resources })
+    }
+}
+
 pub fn dust_main(args: TokenStream) -> TokenStream {
-    let app = syn::parse_macro_input!(args as syn::Path);
+    let DustMainArgs { app, resources } = syn::parse_macro_input!(args as DustMainArgs);
+
+    let serve = match resources {
+        Some(resources) => quote! {
+            ::dust::serve::serve_with_resources(#app, #resources().await).await;
+        },
+        None => quote! {
+            ::dust::serve::serve(#app).await;
+        },
+    };
 
     return quote! {
         #[cfg(feature = "ssr")]
         #[::dust::tokio::main]
         async fn main() {
-            ::dust::serve::serve(#app).await;
+            #serve
         }
     }.into();
-}
\ No newline at end of file
+}
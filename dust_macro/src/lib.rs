@@ -1,5 +1,9 @@
 // extern crate proc_macro;
 
+// Functions end with an explicit `return`.
+#![allow(clippy::needless_return)]
+
+mod computed;
 mod define_callback;
 mod derive_state;
 mod dust_lib;
@@ -13,9 +17,20 @@ pub fn dust_define_callback(args: TokenStream, input: TokenStream) -> TokenStrea
    return define_callback::dust_define_callback(args, input);
 }
 
+#[proc_macro_attribute]
+pub fn dust_define_callbacks(args: TokenStream, input: TokenStream) -> TokenStream {
+   return define_callback::dust_define_callbacks(args, input);
+}
+
+#[proc_macro_attribute]
+pub fn dust_computed(args: TokenStream, input: TokenStream) -> TokenStream {
+   return computed::dust_computed(args, input);
+}
+
 #[proc_macro_derive(
     DustState,
     attributes(
+        dust,
         dust_register_callback,
     )
 )]
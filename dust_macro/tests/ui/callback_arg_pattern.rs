@@ -0,0 +1,8 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+#[dust_define_callback(State)]
+fn compute((a, b): Input<(u32, u32)>, total: &mut Output<u32>) {
+    total.set(a + b);
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+#[dust_define_callback(State)]
+fn compute(value: u32, total: &mut Output<u32>) {
+    total.set(value);
+}
+
+fn main() {}
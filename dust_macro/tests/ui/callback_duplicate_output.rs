@@ -0,0 +1,8 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+struct State;
+
+#[dust_define_callback(State)]
+fn compute(total: &mut Output<u32>, #[field(total)] count: &mut Output<u32>) {}
+
+fn main() {}
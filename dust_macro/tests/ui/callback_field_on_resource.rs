@@ -0,0 +1,8 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+struct State;
+
+#[dust_define_callback(State)]
+fn compute(#[field(database)] db: Resource<Database>, total: &mut Output<u32>) {}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use lucamoller_dust_macro::dust_define_callbacks;
+
+struct State;
+
+#[dust_define_callbacks]
+impl State {
+    #[dust_callback(inputs(price), outputs(total))]
+    fn compute(price: u32, qty: u32) -> u32 {
+        price * qty
+    }
+}
+
+fn main() {}
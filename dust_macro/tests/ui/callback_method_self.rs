@@ -0,0 +1,15 @@
+use lucamoller_dust_macro::dust_define_callbacks;
+
+struct State {
+    price: u32,
+}
+
+#[dust_define_callbacks]
+impl State {
+    #[dust_callback(inputs(price), outputs(total))]
+    fn compute(&self, price: u32) -> u32 {
+        price * 2
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+struct State;
+
+#[dust_define_callback(State, outputs(discount))]
+fn compute(price: Input<u32>) -> Option<Option<u32>> {
+    Some(None)
+}
+
+fn main() {}
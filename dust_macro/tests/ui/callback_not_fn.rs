@@ -0,0 +1,6 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+#[dust_define_callback(State)]
+struct Compute;
+
+fn main() {}
@@ -0,0 +1,10 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+struct State;
+
+#[dust_define_callback(State, outputs(double, triple))]
+fn compute(value: Input<u32>) -> Option<u32> {
+    Some(value.value * 2)
+}
+
+fn main() {}
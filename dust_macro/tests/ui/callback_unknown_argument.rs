@@ -0,0 +1,8 @@
+use lucamoller_dust_macro::dust_define_callback;
+
+#[dust_define_callback(State, gaurd = require_admin())]
+fn compute(value: Input<u32>, total: &mut Output<u32>) {
+    total.set(value.value);
+}
+
+fn main() {}
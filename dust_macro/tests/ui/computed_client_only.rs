@@ -0,0 +1,10 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+struct State {
+    price: u32,
+    #[dust(client_only, computed = double_price)]
+    double: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+struct State {
+    #[dust(nested, max_len = 10)]
+    filters: Filters,
+}
+
+fn main() {}
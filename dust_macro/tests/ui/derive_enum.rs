@@ -0,0 +1,9 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+enum State {
+    A,
+    B,
+}
+
+fn main() {}
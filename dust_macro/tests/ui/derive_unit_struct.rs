@@ -0,0 +1,6 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+struct State;
+
+fn main() {}
@@ -0,0 +1,17 @@
+use lucamoller_dust_macro::DustState;
+
+mod daily {
+    pub struct Revenue;
+}
+
+mod monthly {
+    pub struct Revenue;
+}
+
+#[derive(DustState)]
+#[dust(instance(State<daily::Revenue>, State<monthly::Revenue>))]
+struct State<M> {
+    metric: std::marker::PhantomData<M>,
+}
+
+fn main() {}
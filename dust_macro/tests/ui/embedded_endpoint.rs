@@ -0,0 +1,9 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+#[dust(embedded, endpoint = "address")]
+struct Address {
+    country: String,
+}
+
+fn main() {}
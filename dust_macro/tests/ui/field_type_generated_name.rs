@@ -0,0 +1,10 @@
+use lucamoller_dust_macro::DustState;
+
+struct Value;
+
+#[derive(DustState)]
+struct State {
+    values: Vec<Value>,
+}
+
+fn main() {}
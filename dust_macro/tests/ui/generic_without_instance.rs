@@ -0,0 +1,8 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+pub struct State<T> {
+    pub value: T,
+}
+
+fn main() {}
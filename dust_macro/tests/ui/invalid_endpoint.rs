@@ -0,0 +1,9 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+#[dust(endpoint = "checkout?page=1")]
+struct State {
+    value: u32,
+}
+
+fn main() {}
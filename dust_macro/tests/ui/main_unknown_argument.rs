@@ -0,0 +1,4 @@
+use lucamoller_dust_macro::dust_main;
+
+dust_main!(App, resource = init_resources);
+fn main() {}
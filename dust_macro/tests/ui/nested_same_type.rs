@@ -0,0 +1,11 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+struct State {
+    #[dust(nested)]
+    billing: Address,
+    #[dust(nested)]
+    shipping: Address,
+}
+
+fn main() {}
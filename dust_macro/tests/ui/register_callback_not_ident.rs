@@ -0,0 +1,9 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+#[dust_register_callback("compute")]
+struct State {
+    value: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+struct State {
+    #[dust(server_only, client_only)]
+    value: u32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use lucamoller_dust_macro::DustState;
+
+#[derive(DustState)]
+struct State {
+    #[dust(step = 2)]
+    name: String,
+}
+
+fn main() {}
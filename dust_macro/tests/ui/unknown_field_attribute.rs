@@ -0,0 +1,12 @@
+use lucamoller_dust_macro::DustState;
+
+// Unrelated attributes, including multi-segment ones, are ignored.
+#[rustfmt::skip]
+#[derive(DustState)]
+#[allow(dead_code)]
+struct State {
+    #[dust(server_side)]
+    value: u32,
+}
+
+fn main() {}